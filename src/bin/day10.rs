@@ -1,4 +1,5 @@
-use std::collections::HashSet;
+use std::hash::{BuildHasher, Hasher};
+use std::ops::RangeInclusive;
 
 const INPUT: &str = include_str!("./day10.txt");
 
@@ -11,15 +12,8 @@ const DIRS: &[(i32, i32)] = &[
 
 #[derive(Debug)]
 struct Position {
-    height: u32,
-    rating: Option<u32>,
-    reachable: HashSet<(usize, usize)>,
-}
-
-impl Position {
-    fn is_trailhead(&self) -> bool {
-        self.height == 0
-    }
+    /// `None` for impassable positions (`.`).
+    height: Option<u32>,
 }
 
 #[derive(Debug)]
@@ -27,70 +21,441 @@ struct TopographicMap {
     positions: Vec<Vec<Position>>,
 }
 
+/// Where a trail starts or ends.
+#[derive(Debug, Clone, Copy)]
+enum Endpoint {
+    /// Any position of the given height.
+    Height(u32),
+    /// A single position.
+    At((usize, usize)),
+}
+
+/// What counts as a trail: where it starts, where it ends and which steps it
+/// may take in between. A trail ends at the first goal it reaches, and never
+/// visits a position twice.
+struct TrailRules {
+    from: Endpoint,
+    to: Endpoint,
+    /// Whether a trail may step from a position of height `.0` to an
+    /// adjacent position of height `.1`.
+    step: Box<dyn Fn(u32, u32) -> bool>,
+}
+
+impl Default for TrailRules {
+    /// From height 0 to height 9, going up by exactly 1 at each step.
+    fn default() -> Self {
+        TrailRules {
+            from: Endpoint::Height(0),
+            to: Endpoint::Height(9),
+            step: Box::new(|from, to| to == from + 1),
+        }
+    }
+}
+
+impl TrailRules {
+    /// Allows any step that changes the height by a delta in `deltas`, e.g.,
+    /// `1..=3` for climbs of up to 3 or `-1..=1` to also allow descents and
+    /// level steps. The latter let trails go round in circles, which makes
+    /// them much more numerous (see `analyze`).
+    fn with_deltas(self, deltas: RangeInclusive<i64>) -> Self {
+        TrailRules {
+            step: Box::new(move |from, to| deltas.contains(&(to as i64 - from as i64))),
+            ..self
+        }
+    }
+}
+
+/// Scores and ratings of every position, computed bottom-up (i.e., from the
+/// goals back) so that each position only looks at its direct neighbors.
+///
+/// That needs the positions on trails to form a DAG. If they don't, i.e., a
+/// trail could come back to where it has been, trails are counted one by one
+/// from each trailhead instead.
+struct TrailAnalysis<'a> {
+    map: &'a TopographicMap,
+    rules: &'a TrailRules,
+    goals: Vec<(usize, usize)>,
+    /// Number of `u64` words in each bitset.
+    words: usize,
+    /// Goals reachable from each position, as a bitset over `goals`. The
+    /// bitsets of all positions are laid out back to back, row by row.
+    reachable: Vec<u64>,
+    /// Number of distinct trails from each position to any goal; only
+    /// counted for trailheads and goals if `!acyclic`.
+    ratings: Vec<Vec<u64>>,
+    /// Whether no trail can come back to where it has been, so that the
+    /// trails from a position are the same however it was reached.
+    acyclic: bool,
+}
+
+/// Most steps the trail count of a map with loops can take, in total, before
+/// `analyze` gives up.
+const MAX_COUNT_STEPS: u64 = 1 << 22;
+
 impl TopographicMap {
     #[inline]
-    fn height(&self, x: usize, y: usize) -> u32 {
+    fn height(&self, x: usize, y: usize) -> Option<u32> {
         self.positions[x][y].height
     }
 
     #[inline]
-    fn rating(&self, x: usize, y: usize) -> Option<u32> {
-        self.positions[x][y].rating
+    fn width(&self) -> usize {
+        self.positions.first().map_or(0, |row| row.len())
+    }
+
+    fn coords(&self) -> impl Iterator<Item = (usize, usize)> + '_ {
+        (0..self.positions.len())
+            .flat_map(move |x| (0..self.positions[x].len()).map(move |y| (x, y)))
+    }
+
+    fn is_endpoint(&self, p: (usize, usize), endpoint: Endpoint) -> bool {
+        match endpoint {
+            Endpoint::Height(h) => self.height(p.0, p.1) == Some(h),
+            Endpoint::At(q) => p == q && self.height(p.0, p.1).is_some(),
+        }
+    }
+
+    fn trailheads<'a>(
+        &'a self,
+        rules: &'a TrailRules,
+    ) -> impl Iterator<Item = (usize, usize)> + 'a {
+        self.coords().filter(|&p| self.is_endpoint(p, rules.from))
     }
 
     #[inline]
-    fn next_positions(&self, curr: (usize, usize)) -> Vec<(usize, usize)> {
+    fn next_positions(
+        &self,
+        curr: (usize, usize),
+        rules: &TrailRules,
+    ) -> Vec<(usize, usize)> {
+        let Some(height) = self.height(curr.0, curr.1) else {
+            return Vec::new();
+        };
+
         DIRS.iter()
             .filter_map(|&(dx, dy)| {
                 let x = (curr.0 as i32) + dx;
                 let y = (curr.1 as i32) + dy;
 
                 if x < 0 || x >= (self.positions.len() as i32) ||
-                    y < 0 || y >= (self.positions[0].len() as i32) {
+                    y < 0 || y >= (self.width() as i32) {
                     return None;
                 }
 
                 let x = x as usize;
                 let y = y as usize;
 
-                if self.height(x, y) == self.height(curr.0, curr.1) + 1 {
-                    Some((x, y))
-                } else {
-                    None
+                match self.height(x, y) {
+                    Some(next) if (rules.step)(height, next) => Some((x, y)),
+                    _ => None,
                 }
             })
             .collect::<Vec<_>>()
     }
 
-    fn update(&mut self, curr: (usize, usize)) {
-        // FIXME(ugly)
-        if self.positions[curr.0][curr.1].height == 9 {
-            self.positions[curr.0][curr.1].reachable.insert(curr);
-            self.positions[curr.0][curr.1].rating.replace(1);
-            return;
+    /// Returns an error if the trails can come back to where they've been,
+    /// and there are too many of them to count one by one.
+    fn analyze<'a>(
+        &'a self,
+        rules: &'a TrailRules,
+    ) -> Result<TrailAnalysis<'a>, &'static str> {
+        let width = self.width();
+        let index = |(x, y): (usize, usize)| x * width + y;
+        let coords = self.coords().collect::<Vec<_>>();
+
+        // Goals are terminal, so they have no successors.
+        let succs = coords.iter()
+            .map(|&p| if self.is_endpoint(p, rules.to) {
+                Vec::new()
+            } else {
+                self.next_positions(p, rules)
+            })
+            .collect::<Vec<_>>();
+
+        let mut preds = vec![Vec::new(); coords.len()];
+
+        for (&p, succs) in coords.iter().zip(&succs) {
+            for &n in succs {
+                preds[index(n)].push(p);
+            }
         }
 
-        if let Some(_) = self.positions[curr.0][curr.1].rating {
-            return;
+        let flood = |seeds: Vec<(usize, usize)>, edges: &[Vec<(usize, usize)>]| {
+            let mut seen = vec![false; coords.len()];
+            let mut stack = seeds;
+
+            while let Some(p) = stack.pop() {
+                if !std::mem::replace(&mut seen[index(p)], true) {
+                    stack.extend(&edges[index(p)]);
+                }
+            }
+
+            seen
+        };
+
+        let goals = self.coords()
+            .filter(|&p| self.is_endpoint(p, rules.to))
+            .collect::<Vec<_>>();
+
+        let forward = flood(self.trailheads(rules).collect(), &succs);
+        let backward = flood(goals.clone(), &preds);
+
+        // Only positions on some trail matter.
+        let relevant = forward.iter().zip(&backward)
+            .map(|(&f, &b)| f && b)
+            .collect::<Vec<_>>();
+
+        // Kahn's algorithm over the relevant positions.
+        let mut indegree = vec![0usize; coords.len()];
+
+        for &p in coords.iter().filter(|&&p| relevant[index(p)]) {
+            for &n in succs[index(p)].iter().filter(|&&n| relevant[index(n)]) {
+                indegree[index(n)] += 1;
+            }
+        }
+
+        let mut order = coords.iter()
+            .copied()
+            .filter(|&p| relevant[index(p)] && indegree[index(p)] == 0)
+            .collect::<Vec<_>>();
+
+        let mut i = 0;
+
+        while i < order.len() {
+            for &n in succs[index(order[i])].iter().filter(|&&n| relevant[index(n)]) {
+                indegree[index(n)] -= 1;
+
+                if indegree[index(n)] == 0 {
+                    order.push(n);
+                }
+            }
+
+            i += 1;
+        }
+
+        let acyclic = order.len() == relevant.iter().filter(|&&r| r).count();
+
+        let words = goals.len().div_ceil(64);
+
+        let bitset = |p: (usize, usize)| index(p) * words;
+
+        let mut reachable = vec![0u64; coords.len() * words];
+
+        let mut ratings = self.positions.iter()
+            .map(|row| vec![0u64; row.len()])
+            .collect::<Vec<_>>();
+
+        for (i, &(x, y)) in goals.iter().enumerate() {
+            reachable[bitset((x, y)) + i / 64] |= 1 << (i % 64);
+            ratings[x][y] = 1;
+        }
+
+        if acyclic {
+            // Visiting positions in reverse topological order guarantees that
+            // all successors are already done.
+            for &curr in order.iter().rev() {
+                for &n in &succs[index(curr)] {
+                    let (to, from) = (bitset(curr), bitset(n));
+
+                    for w in 0..words {
+                        reachable[to + w] |= reachable[from + w];
+                    }
+
+                    ratings[curr.0][curr.1] = ratings[curr.0][curr.1]
+                        .checked_add(ratings[n.0][n.1])
+                        .ok_or("too many trails")?;
+                }
+            }
+
+            return Ok(TrailAnalysis { map: self, rules, goals, words, reachable, ratings, acyclic });
+        }
+
+        // Goals reachable from a position are still those of its successors,
+        // but they have to be passed back until nothing changes.
+        let mut changed = goals.clone();
+
+        while let Some(n) = changed.pop() {
+            for &p in &preds[index(n)] {
+                let (to, from) = (bitset(p), bitset(n));
+
+                let mut grew = false;
+
+                for w in 0..words {
+                    grew |= reachable[from + w] & !reachable[to + w] != 0;
+                    reachable[to + w] |= reachable[from + w];
+                }
+
+                if grew {
+                    changed.push(p);
+                }
+            }
+        }
+
+        // A trail from a trailhead can't go back through any position it has
+        // visited, so they're counted by trying every way forward.
+        let mut steps = MAX_COUNT_STEPS;
+        let mut on_trail = vec![false; coords.len()];
+
+        for start in self.trailheads(rules).filter(|&p| relevant[index(p)]) {
+            let mut count = 0u64;
+
+            // Each entry is a position on the current trail and the index of
+            // its next successor to try.
+            let mut stack = vec![(start, 0)];
+
+            on_trail[index(start)] = true;
+
+            while let Some(top) = stack.last_mut() {
+                let (p, i) = *top;
+
+                if let Some(&n) = succs[index(p)].get(i) {
+                    top.1 += 1;
+
+                    if relevant[index(n)] && !on_trail[index(n)] {
+                        steps = steps.checked_sub(1).ok_or("too many trails to count")?;
+
+                        on_trail[index(n)] = true;
+                        stack.push((n, 0));
+                    }
+
+                    continue;
+                }
+
+                // Goals have no successors.
+                if self.is_endpoint(p, rules.to) {
+                    count = count.checked_add(1).ok_or("too many trails")?;
+                }
+
+                on_trail[index(p)] = false;
+                stack.pop();
+            }
+
+            ratings[start.0][start.1] = count;
+        }
+
+        Ok(TrailAnalysis { map: self, rules, goals, words, reachable, ratings, acyclic })
+    }
+}
+
+impl TrailAnalysis<'_> {
+    /// Number of distinct goals reachable from `p`.
+    fn score(&self, p: (usize, usize)) -> u32 {
+        self.bitset(p).iter().map(|word| word.count_ones()).sum()
+    }
+
+    fn bitset(&self, p: (usize, usize)) -> &[u64] {
+        let start = (p.0 * self.map.width() + p.1) * self.words;
+
+        &self.reachable[start..start + self.words]
+    }
+
+    fn reachable_goals(&self, p: (usize, usize)) -> Vec<(usize, usize)> {
+        let bitset = self.bitset(p);
+
+        self.goals.iter().enumerate()
+            .filter(|(i, _)| bitset[i / 64] & (1 << (i % 64)) != 0)
+            .map(|(_, &goal)| goal)
+            .collect()
+    }
+
+    /// Number of distinct trails from `p` to any goal.
+    fn rating(&self, p: (usize, usize)) -> u64 {
+        self.ratings[p.0][p.1]
+    }
+
+    fn is_goal(&self, p: (usize, usize)) -> bool {
+        self.map.is_endpoint(p, self.rules.to)
+    }
+
+    /// Successors of `p` from which a goal can still be reached.
+    fn live_next_positions(&self, p: (usize, usize)) -> Vec<(usize, usize)> {
+        self.map.next_positions(p, self.rules).into_iter()
+            .filter(|&n| self.score(n) > 0)
+            .collect()
+    }
+
+    /// Enumerates every distinct trail from `start` as a list of coordinates.
+    /// There are `self.rating(start)` of them, which can be a lot.
+    fn trails(&self, start: (usize, usize)) -> Vec<Vec<(usize, usize)>> {
+        let mut trails = Vec::new();
+
+        if self.rating(start) == 0 {
+            return trails;
+        }
+
+        // Each stack entry is a partial trail and the successors of its last
+        // position that are yet to be tried.
+        let mut stack = vec![(vec![start], self.live_next_positions(start))];
+
+        while let Some((trail, mut pending)) = stack.pop() {
+            let last = *trail.last().unwrap();
+
+            if self.is_goal(last) {
+                trails.push(trail);
+                continue;
+            }
+
+            if let Some(n) = pending.pop() {
+                let mut next = trail.clone();
+                next.push(n);
+
+                let next_pending = self.live_next_positions(n).into_iter()
+                    .filter(|m| !next.contains(m))
+                    .collect();
+
+                stack.push((trail, pending));
+                stack.push((next, next_pending));
+            }
+        }
+
+        trails
+    }
+
+    /// Returns the `k`-th trail from `start` (for `k < self.rating(start)`)
+    /// in the same order as `trails`, without enumerating the ones before it.
+    fn nth_trail(&self, start: (usize, usize), mut k: u64) -> Option<Vec<(usize, usize)>> {
+        if k >= self.rating(start) {
+            return None;
+        }
+
+        // The trails from a position depend on how it was reached.
+        if !self.acyclic {
+            return self.trails(start).into_iter().nth(k as usize);
+        }
+
+        let mut trail = vec![start];
+        let mut curr = start;
+
+        while !self.is_goal(curr) {
+            // `trails` tries the successors in reverse order.
+            for n in self.live_next_positions(curr).into_iter().rev() {
+                if k < self.rating(n) {
+                    curr = n;
+                    break;
+                }
+
+                k -= self.rating(n);
+            }
+
+            trail.push(curr);
         }
 
-        self.positions[curr.0][curr.1].rating = Some(0);
+        Some(trail)
+    }
+
+    /// Picks a trail from `start` uniformly at random.
+    fn sample_trail(&self, start: (usize, usize)) -> Option<Vec<(usize, usize)>> {
+        let rating = self.rating(start);
 
-        self.next_positions(curr).iter().for_each(|&n| {
-            self.update(n);
+        if rating == 0 {
+            return None;
+        }
 
-            // part1: score (i.e., # reachable peaks)
-            // FIXME(efficiency): clone
-            let reachable_from_n = &self.positions[n.0][n.1].reachable.clone();
-            self.positions[curr.0][curr.1].reachable.extend(reachable_from_n);
+        let random = std::collections::hash_map::RandomState::new()
+            .build_hasher()
+            .finish();
 
-            // part 2: rating
-            let new_rating = match self.positions[curr.0][curr.1].rating {
-                Some(rating) => rating + self.rating(n.0, n.1).unwrap(),
-                None => self.rating(n.0, n.1).unwrap()
-            };
-            self.positions[curr.0][curr.1].rating.replace(new_rating);
-        });
+        self.nth_trail(start, random % rating)
     }
 }
 
@@ -101,47 +466,296 @@ impl TryFrom<&str> for TopographicMap {
         let positions = input.lines()
             .map(|row| {
                 row.chars()
-                    .map(|c| Position {
-                        height: c.to_digit(10).unwrap(),
-                        rating: None,
-                        reachable: HashSet::new(),
+                    .map(|c| match c {
+                        '.' => Ok(Position { height: None }),
+                        c => Ok(Position {
+                            height: Some(c.to_digit(10).ok_or("invalid height")?),
+                        }),
                     })
-                    .collect::<Vec<_>>()
+                    .collect::<Result<Vec<_>, _>>()
             })
-            .collect::<Vec<_>>();
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let width = positions.first().map_or(0, Vec::len);
+
+        if positions.iter().any(|row| row.len() != width) {
+            return Err("rows differ in length");
+        }
 
         Ok(TopographicMap { positions })
     }
 }
 
-fn main() {
-    let mut topomap: TopographicMap = INPUT.try_into().unwrap();
+fn parse_coord(arg: &str) -> Option<(usize, usize)> {
+    let (x, y) = arg.split_once(',')?;
 
-    for i in 0..topomap.positions.len() {
-        for j in 0..topomap.positions[0].len() {
-            topomap.update((i, j));
-        }
+    Some((x.parse().ok()?, y.parse().ok()?))
+}
+
+/// Parses either a height (`7`) or a position (`3,4`).
+fn parse_endpoint(arg: &str) -> Option<Endpoint> {
+    match parse_coord(arg) {
+        Some(p) => Some(Endpoint::At(p)),
+        None => Some(Endpoint::Height(arg.parse().ok()?)),
     }
+}
+
+/// Parses an inclusive range of height deltas, e.g., `-1..=2`.
+fn parse_deltas(arg: &str) -> Option<RangeInclusive<i64>> {
+    let (lo, hi) = arg.split_once("..=")?;
 
-    let sum_trailhead_scores = topomap.positions.iter()
-        .map(|row| {
-            row.iter()
-                .filter(|p| p.is_trailhead())
-                .map(|p| p.reachable.len() as u32)
-                .sum::<u32>()
-        })
+    Some(lo.parse().ok()?..=hi.parse().ok()?)
+}
+
+fn main() {
+    let topomap: TopographicMap = INPUT.try_into().unwrap();
+
+    let mut rules = TrailRules::default();
+
+    let analysis = topomap.analyze(&rules).unwrap();
+
+    let sum_trailhead_scores = topomap.trailheads(&rules)
+        .map(|p| analysis.score(p))
         .sum::<u32>();
 
     println!("{}", sum_trailhead_scores);
 
-    let sum_trailhead_ratings = topomap.positions.iter()
-        .map(|row| {
-            row.iter()
-                .filter(|p| p.is_trailhead())
-                .map(|p| p.rating.unwrap_or(0))
-                .sum::<u32>()
-        })
-        .sum::<u32>();
+    let sum_trailhead_ratings = topomap.trailheads(&rules)
+        .map(|p| analysis.rating(p))
+        .sum::<u64>();
 
     println!("{}", sum_trailhead_ratings);
-}
\ No newline at end of file
+
+    // e.g., `cargo run --bin day10 -- --steps 1..=2 --from 0,2 --to 9 --trails`
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+
+    if args.is_empty() {
+        return;
+    }
+
+    let mut list_trails = false;
+    let mut sample_trail = false;
+
+    let mut args = args.iter();
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--steps" => {
+                let deltas = args.next().and_then(|arg| parse_deltas(arg))
+                    .expect("expected a range like `-1..=2`");
+
+                rules = rules.with_deltas(deltas);
+            },
+            "--from" => {
+                rules.from = args.next().and_then(|arg| parse_endpoint(arg))
+                    .expect("expected a height or a coordinate like `x,y`");
+            },
+            "--to" => {
+                rules.to = args.next().and_then(|arg| parse_endpoint(arg))
+                    .expect("expected a height or a coordinate like `x,y`");
+            },
+            "--trails" => list_trails = true,
+            "--sample" => sample_trail = true,
+            _ => panic!("unknown option"),
+        }
+    }
+
+    let analysis = match topomap.analyze(&rules) {
+        Ok(analysis) => analysis,
+        Err(e) => {
+            println!("error: {}", e);
+            return;
+        },
+    };
+
+    for start in topomap.trailheads(&rules) {
+        if analysis.rating(start) == 0 {
+            continue;
+        }
+
+        println!(
+            "{:?}: score={} rating={} goals={:?}",
+            start, analysis.score(start), analysis.rating(start),
+            analysis.reachable_goals(start),
+        );
+
+        let trails = if list_trails {
+            analysis.trails(start)
+        } else if sample_trail {
+            analysis.sample_trail(start).into_iter().collect()
+        } else {
+            Vec::new()
+        };
+
+        for trail in trails {
+            println!("{:?}", trail);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashSet;
+
+    use super::*;
+
+    const SAMPLE: &str = include_str!("./day10-sample2.txt");
+
+    #[test]
+    fn test_scores_and_ratings() {
+        let topomap: TopographicMap = SAMPLE.try_into().unwrap();
+
+        let rules = TrailRules::default();
+
+        let analysis = topomap.analyze(&rules).unwrap();
+
+        assert_eq!(topomap.trailheads(&rules).map(|p| analysis.score(p)).sum::<u32>(), 36);
+        assert_eq!(topomap.trailheads(&rules).map(|p| analysis.rating(p)).sum::<u64>(), 81);
+    }
+
+    #[test]
+    fn test_trails() {
+        let topomap: TopographicMap = SAMPLE.try_into().unwrap();
+
+        let rules = TrailRules::default();
+
+        let analysis = topomap.analyze(&rules).unwrap();
+
+        for start in topomap.trailheads(&rules) {
+            let trails = analysis.trails(start);
+
+            assert_eq!(trails.len() as u64, analysis.rating(start));
+
+            for (k, trail) in trails.iter().enumerate() {
+                assert_eq!(trail.len(), 10);
+                assert_eq!(analysis.nth_trail(start, k as u64).as_ref(), Some(trail));
+            }
+        }
+    }
+
+    #[test]
+    fn test_impassable() {
+        let topomap: TopographicMap = "\
+...0...
+...1...
+...2...
+6543456
+7.....7
+8.....8
+9.....9".try_into().unwrap();
+
+        let rules = TrailRules::default();
+
+        let analysis = topomap.analyze(&rules).unwrap();
+
+        assert_eq!(analysis.score((0, 3)), 2);
+    }
+
+    #[test]
+    fn test_custom_rules() {
+        let topomap: TopographicMap = "\
+0135
+1.46
+2357".try_into().unwrap();
+
+        let rules = TrailRules {
+            from: Endpoint::At((0, 0)),
+            to: Endpoint::At((2, 3)),
+            ..TrailRules::default()
+        };
+
+        let analysis = topomap.analyze(&rules).unwrap();
+
+        assert_eq!(analysis.rating((0, 0)), 0);
+
+        let rules = rules.with_deltas(1..=2);
+
+        let analysis = topomap.analyze(&rules).unwrap();
+
+        assert_eq!(analysis.rating((0, 0)), 4);
+
+        let rules = rules.with_deltas(-1..=2);
+
+        let analysis = topomap.analyze(&rules).unwrap();
+
+        assert_eq!(analysis.rating((0, 0)), 6);
+        assert_eq!(analysis.trails((0, 0)).len(), 6);
+    }
+
+    /// Counts the trails from `p` by trying every way forward, and collects
+    /// the goals they reach.
+    fn brute_force(
+        topomap: &TopographicMap,
+        rules: &TrailRules,
+        trail: &mut Vec<(usize, usize)>,
+        goals: &mut HashSet<(usize, usize)>,
+    ) -> u64 {
+        let p = *trail.last().unwrap();
+
+        if topomap.is_endpoint(p, rules.to) {
+            goals.insert(p);
+            return 1;
+        }
+
+        let mut count = 0;
+
+        for n in topomap.next_positions(p, rules) {
+            if !trail.contains(&n) {
+                trail.push(n);
+                count += brute_force(topomap, rules, trail, goals);
+                trail.pop();
+            }
+        }
+
+        count
+    }
+
+    #[test]
+    fn test_loops() {
+        let topomap: TopographicMap = "\
+0121
+1.23
+2349
+9.89".try_into().unwrap();
+
+        for deltas in [1..=1, 1..=2, -1..=1, -1..=2, 0..=1, -2..=2] {
+            let rules = TrailRules::default().with_deltas(deltas.clone());
+
+            let analysis = topomap.analyze(&rules).unwrap();
+
+            for start in topomap.trailheads(&rules) {
+                let mut goals = HashSet::new();
+
+                let rating = brute_force(&topomap, &rules, &mut vec![start], &mut goals);
+
+                assert_eq!(analysis.rating(start), rating, "{:?}", deltas);
+                assert_eq!(analysis.score(start), goals.len() as u32, "{:?}", deltas);
+
+                let trails = analysis.trails(start);
+
+                assert_eq!(trails.len() as u64, rating);
+
+                for (k, trail) in trails.iter().enumerate() {
+                    assert_eq!(analysis.nth_trail(start, k as u64).as_ref(), Some(trail));
+                }
+            }
+        }
+
+        // Every trail on a big enough open map is too many to count.
+        let open = vec!["5".repeat(12); 12].join("\n");
+        let topomap: TopographicMap = open.as_str().try_into().unwrap();
+
+        let rules = TrailRules {
+            from: Endpoint::At((0, 0)),
+            to: Endpoint::At((11, 11)),
+            ..TrailRules::default().with_deltas(0..=0)
+        };
+
+        assert_eq!(topomap.analyze(&rules).err(), Some("too many trails to count"));
+    }
+
+    #[test]
+    fn test_ragged_rows() {
+        assert_eq!(TopographicMap::try_from("012\n34").err(), Some("rows differ in length"));
+    }
+}
@@ -8,45 +8,525 @@ const K1: usize = 25;
 
 const K2: usize = 75;
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 struct Stone(u64);
 
-impl Stone {
-    // F(n, k) = (# stones after blinking stone N k times)
-    //
-    // - forall n. F(n, 0) = 1
-    // - forall n >= 1. if let (hi, lo) = n -> F(n, k) = F(hi, k) + F(lo, k - 1)
-    //                  else if n = 0 -> F(n, k) = F(1, k - 1)
-    //                  else F(n, k) = F(2024 * n, k - 1)
-    fn blink_k(
-        &self,
-        k: usize,
-        cache: &mut HashMap<(u64, usize), usize>
-    ) -> usize {
-        if let Some(&n) = cache.get(&(self.0, k)) {
-            return n;
+fn num_digits(n: u64) -> u32 {
+    n.checked_ilog10().map_or(1, |d| d + 1)
+}
+
+const OVERFLOW: &str = "stone value overflow";
+
+/// Which stones a rule applies to.
+#[derive(Debug, Clone, PartialEq)]
+enum Predicate {
+    /// `*`
+    Any,
+    /// `<n>`
+    Eq(u64),
+    /// `even`: stones with an even number of digits.
+    EvenDigits,
+    /// `%<n>`: stones divisible by `n`.
+    DivisibleBy(u64),
+}
+
+/// What a stone turns into. A rule can have several transforms, each of
+/// which produces stones in order.
+#[derive(Debug, Clone, PartialEq)]
+enum Transform {
+    /// `<n>`: a stone engraved with `n`.
+    Set(u64),
+    /// `split`: two stones with the left and right halves of the digits. For
+    /// an odd number of digits, the left half gets the extra digit.
+    Split,
+    /// `*<n>`
+    Mul(u64),
+    /// `+<n>`
+    Add(u64),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Rule {
+    when: Predicate,
+    then: Vec<Transform>,
+}
+
+/// An ordered list of rules; the first one that applies to a stone wins, and
+/// a stone no rule applies to stays as is.
+///
+/// Rules can be parsed from a spec with one `<predicate> -> <transform>, ...`
+/// rule per line, e.g., the puzzle's rules are:
+///
+/// ```text
+/// 0 -> 1
+/// even -> split
+/// * -> *2024
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+struct Rules(Vec<Rule>);
+
+impl Predicate {
+    fn matches(&self, stone: Stone) -> bool {
+        match *self {
+            Predicate::Any => true,
+            Predicate::Eq(n) => stone.0 == n,
+            Predicate::EvenDigits => num_digits(stone.0).is_multiple_of(2),
+            Predicate::DivisibleBy(n) => n != 0 && stone.0.is_multiple_of(n),
+        }
+    }
+}
+
+impl Transform {
+    fn apply(&self, stone: Stone, out: &mut Vec<Stone>) -> Result<(), &'static str> {
+        match *self {
+            Transform::Set(n) => out.push(Stone(n)),
+            Transform::Split => {
+                let half = 10u64.pow(num_digits(stone.0) / 2);
+
+                out.push(Stone(stone.0 / half));
+                out.push(Stone(stone.0 % half));
+            },
+            Transform::Mul(n) => out.push(Stone(stone.0.checked_mul(n).ok_or(OVERFLOW)?)),
+            Transform::Add(n) => out.push(Stone(stone.0.checked_add(n).ok_or(OVERFLOW)?)),
+        }
+
+        Ok(())
+    }
+}
+
+impl Rules {
+    /// Returns the stones `stone` turns into after a single blink.
+    fn apply(&self, stone: Stone) -> Result<Vec<Stone>, &'static str> {
+        let Some(rule) = self.0.iter().find(|rule| rule.when.matches(stone)) else {
+            return Ok(vec![stone]);
+        };
+
+        let mut out = Vec::with_capacity(rule.then.len());
+
+        for transform in &rule.then {
+            transform.apply(stone, &mut out)?;
+        }
+
+        Ok(out)
+    }
+}
+
+impl Default for Rules {
+    fn default() -> Self {
+        Rules(vec![
+            Rule { when: Predicate::Eq(0), then: vec![Transform::Set(1)] },
+            Rule { when: Predicate::EvenDigits, then: vec![Transform::Split] },
+            Rule { when: Predicate::Any, then: vec![Transform::Mul(MAGIC)] },
+        ])
+    }
+}
+
+impl TryFrom<&str> for Predicate {
+    type Error = &'static str;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        match value {
+            "*" => Ok(Predicate::Any),
+            "even" => Ok(Predicate::EvenDigits),
+            _ => {
+                if let Some(n) = value.strip_prefix('%') {
+                    n.parse().map(Predicate::DivisibleBy).map_err(|_| "invalid divisor")
+                } else {
+                    value.parse().map(Predicate::Eq).map_err(|_| "invalid predicate")
+                }
+            },
+        }
+    }
+}
+
+impl TryFrom<&str> for Transform {
+    type Error = &'static str;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        if value == "split" {
+            Ok(Transform::Split)
+        } else if let Some(n) = value.strip_prefix('*') {
+            n.parse().map(Transform::Mul).map_err(|_| "invalid multiplier")
+        } else if let Some(n) = value.strip_prefix('+') {
+            n.parse().map(Transform::Add).map_err(|_| "invalid addend")
+        } else {
+            value.parse().map(Transform::Set).map_err(|_| "invalid transform")
+        }
+    }
+}
+
+impl TryFrom<&str> for Rules {
+    type Error = &'static str;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        value.lines()
+            .map(|line| line.trim())
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .map(|line| {
+                let (when, then) = line.split_once("->").ok_or("missing '->' in rule")?;
+
+                let then = then.split(',')
+                    .map(|transform| Transform::try_from(transform.trim()))
+                    .collect::<Result<Vec<_>, _>>()?;
+
+                Ok(Rule { when: Predicate::try_from(when.trim())?, then })
+            })
+            .collect::<Result<Vec<_>, _>>()
+            .map(Rules)
+    }
+}
+
+/// Number of stones of each value in one generation. The order of stones
+/// doesn't affect how they change, so it's not tracked.
+#[derive(Debug, Clone)]
+struct Stones {
+    rules: Rules,
+    /// Memoised results of `Rules::apply`.
+    transitions: HashMap<Stone, Vec<Stone>>,
+    counts: HashMap<Stone, u128>,
+    /// If set, counts are only tracked modulo this value, which allows
+    /// blinking any number of times without overflowing.
+    modulus: Option<u128>,
+    blinks: usize,
+}
+
+#[derive(Debug, PartialEq)]
+struct Generation {
+    blinks: usize,
+    /// Total number of stones (modulo `Stones::modulus`, if any).
+    total: u128,
+    /// Number of distinct stone values.
+    distinct: usize,
+}
+
+impl Stones {
+    fn new(stones: &[Stone], rules: Rules, modulus: Option<u128>) -> Self {
+        let mut counts = HashMap::new();
+
+        for &stone in stones {
+            *counts.entry(stone).or_insert(0) += 1;
+        }
+
+        let mut stones = Stones {
+            rules,
+            transitions: HashMap::new(),
+            counts,
+            modulus,
+            blinks: 0,
+        };
+
+        if let Some(m) = modulus {
+            stones.counts.values_mut().for_each(|count| *count %= m);
+        }
+
+        stones
+    }
+
+    fn add(&self, lhs: u128, rhs: u128) -> Result<u128, &'static str> {
+        match self.modulus {
+            // Both operands are already reduced, so `lhs + rhs < 2m`.
+            Some(m) => Ok(lhs.checked_add(rhs).map_or_else(
+                || lhs - (m - rhs),
+                |sum| sum % m,
+            )),
+            None => lhs.checked_add(rhs).ok_or("stone count overflow"),
+        }
+    }
+
+    fn blink(&mut self) -> Result<(), &'static str> {
+        let mut next = HashMap::with_capacity(self.counts.len());
+
+        for (&stone, &count) in &self.counts {
+            if !self.transitions.contains_key(&stone) {
+                self.transitions.insert(stone, self.rules.apply(stone)?);
+            }
+
+            for &stone in &self.transitions[&stone] {
+                let entry = next.entry(stone).or_insert(0);
+                *entry = self.add(*entry, count)?;
+            }
+        }
+
+        self.counts = next;
+        self.blinks += 1;
+
+        Ok(())
+    }
+
+    fn generation(&self) -> Result<Generation, &'static str> {
+        let total = self.counts.values()
+            .try_fold(0, |acc, &count| self.add(acc, count))?;
+
+        Ok(Generation { blinks: self.blinks, total, distinct: self.counts.len() })
+    }
+
+    /// Blinks `k` times, reporting every generation (including the current
+    /// one) along the way.
+    fn simulate(&mut self, k: usize) -> Result<Vec<Generation>, &'static str> {
+        let mut generations = vec![self.generation()?];
+
+        for _ in 0..k {
+            self.blink()?;
+            generations.push(self.generation()?);
+        }
+
+        Ok(generations)
+    }
+}
+
+/// The transition graph over every stone value reachable from the initial
+/// stones. Once the set of values is closed, a blink is a linear map: with
+/// `M[j][i]` the number of stones of value `values[j]` that a stone of value
+/// `values[i]` turns into and `v` the initial counts, the number of stones
+/// after `k` blinks is `1ᵀ Mᵏ v`.
+#[derive(Debug)]
+struct TransitionGraph {
+    values: Vec<Stone>,
+    /// Indices into `values` of the stones each value turns into, i.e., the
+    /// non-zero entries of each column of `M` (with multiplicity).
+    next: Vec<Vec<usize>>,
+    /// Initial number of stones of each value, i.e., `v`.
+    initial: Vec<u64>,
+    /// Number of blinks after which no new stone values appear.
+    closed_after: usize,
+}
+
+impl TransitionGraph {
+    /// Explores the values reachable from `stones` breadth-first, giving up
+    /// if there are more than `max_values` of them.
+    fn new(stones: &[Stone], rules: &Rules, max_values: usize) -> Result<Self, &'static str> {
+        let mut index = HashMap::new();
+        let mut values = Vec::new();
+        let mut initial = Vec::new();
+
+        for &stone in stones {
+            let i = *index.entry(stone).or_insert_with(|| {
+                values.push(stone);
+                initial.push(0);
+                values.len() - 1
+            });
+
+            initial[i] += 1;
+        }
+
+        let mut next = Vec::new();
+        let mut closed_after = 0;
+        let mut frontier = 0..values.len();
+
+        while !frontier.is_empty() {
+            let start = values.len();
+
+            for i in frontier {
+                let mut edges = Vec::new();
+
+                for stone in rules.apply(values[i])? {
+                    let j = *index.entry(stone).or_insert_with(|| {
+                        values.push(stone);
+                        values.len() - 1
+                    });
+
+                    edges.push(j);
+                }
+
+                next.push(edges);
+            }
+
+            if values.len() > max_values {
+                return Err("the set of stone values doesn't close");
+            }
+
+            if values.len() > start {
+                closed_after += 1;
+            }
+
+            frontier = start..values.len();
+        }
+
+        initial.resize(values.len(), 0);
+
+        Ok(TransitionGraph { values, next, initial, closed_after })
+    }
+
+    /// Number of stones after `0..len` blinks, modulo `p`.
+    fn counts_mod(&self, len: usize, p: u64) -> Vec<u64> {
+        let mut v = self.initial.iter().map(|&n| n % p).collect::<Vec<_>>();
+        let mut counts = Vec::with_capacity(len);
+
+        for _ in 0..len {
+            counts.push(v.iter().fold(0, |acc, &n| (acc + n) % p));
+
+            let mut w = vec![0; v.len()];
+
+            for (i, &n) in v.iter().enumerate() {
+                for &j in &self.next[i] {
+                    w[j] = (w[j] + n) % p;
+                }
+            }
+
+            v = w;
         }
 
-        if k == 0 {
-            return 1;
+        counts
+    }
+
+    /// Number of stones after `k` blinks, modulo a prime `p < 2^32`.
+    ///
+    /// `M` is far too big to exponentiate directly, but by Cayley-Hamilton
+    /// the counts satisfy a linear recurrence of order at most `n` (the
+    /// number of values). Berlekamp-Massey recovers it from the first `2n`
+    /// counts, and the `k`-th term then takes `O(d² log k)` for a recurrence
+    /// of order `d`.
+    fn count_mod(&self, k: u64, p: u64) -> Result<u64, &'static str> {
+        if !is_prime(p) || p >= 1 << 32 {
+            return Err("modulus must be a prime below 2^32");
+        }
+
+        let len = 2 * self.values.len() + 1;
+
+        if k < len as u64 {
+            return Ok(self.counts_mod(k as usize + 1, p)[k as usize]);
+        }
+
+        let counts = self.counts_mod(len, p);
+
+        let recurrence = berlekamp_massey(&counts, p);
+
+        Ok(nth_term(&recurrence, &counts, k, p))
+    }
+}
+
+fn is_prime(p: u64) -> bool {
+    p >= 2 && (2..).take_while(|d| d * d <= p).all(|d| !p.is_multiple_of(d))
+}
+
+fn pow_mod(mut base: u64, mut exp: u64, p: u64) -> u64 {
+    let mut res = 1 % p;
+
+    base %= p;
+
+    while exp > 0 {
+        if exp & 1 == 1 {
+            res = res * base % p;
         }
 
-        let mut res = 0usize;
+        base = base * base % p;
+        exp >>= 1;
+    }
+
+    res
+}
+
+/// Returns `c` such that `s[i] = c[0] s[i - 1] + ... + c[d - 1] s[i - d]`
+/// (mod `p`) for all `i >= d`, with `d` as small as possible.
+fn berlekamp_massey(s: &[u64], p: u64) -> Vec<u64> {
+    let mut c = vec![1u64];
+    let mut b = vec![1u64];
+    let mut len = 0;
+    let mut shift = 1;
+    let mut last = 1u64;
+
+    for i in 0..s.len() {
+        // Discrepancy between `s[i]` and what the current recurrence predicts.
+        let d = (0..=len).fold(0, |acc, j| (acc + c[j] * s[i - j]) % p);
+
+        if d == 0 {
+            shift += 1;
+            continue;
+        }
+
+        let coef = d * pow_mod(last, p - 2, p) % p;
+
+        let prev = c.clone();
+
+        if c.len() < b.len() + shift {
+            c.resize(b.len() + shift, 0);
+        }
 
-        if self.0.to_string().len() % 2 == 0 {
-            let s = self.0.to_string();
+        for (j, &bj) in b.iter().enumerate() {
+            c[j + shift] = (c[j + shift] + p - coef * bj % p) % p;
+        }
 
-            res += Stone(s[0..(s.len() / 2)].parse().unwrap()).blink_k(k - 1, cache);
-            res += Stone(s[(s.len() / 2)..s.len()].parse().unwrap()).blink_k(k - 1, cache);
-        } else if self.0 == 0 {
-            res += Stone(1).blink_k(k - 1, cache);
+        if 2 * len <= i {
+            len = i + 1 - len;
+            b = prev;
+            last = d;
+            shift = 1;
         } else {
-            res += Stone(self.0 * MAGIC).blink_k(k - 1, cache);
+            shift += 1;
+        }
+    }
+
+    c.truncate(len + 1);
+    c.resize(len + 1, 0);
+
+    c[1..].iter().map(|&cj| (p - cj) % p).collect()
+}
+
+/// Computes the `k`-th term of the linear recurrence `c` with initial terms
+/// `s` by reducing `x^k` modulo its characteristic polynomial.
+fn nth_term(c: &[u64], s: &[u64], k: u64, p: u64) -> u64 {
+    let d = c.len();
+
+    if d == 0 {
+        return 0;
+    }
+
+    // Multiplies two polynomials of degree < d modulo
+    // `x^d - c[0] x^(d-1) - ... - c[d-1]`.
+    let mul = |a: &[u64], b: &[u64]| {
+        let mut prod = vec![0u64; 2 * d - 1];
+
+        for (i, &ai) in a.iter().enumerate().filter(|(_, &ai)| ai != 0) {
+            for (j, &bj) in b.iter().enumerate() {
+                prod[i + j] = (prod[i + j] + ai * bj) % p;
+            }
+        }
+
+        for i in (d..2 * d - 1).rev() {
+            let top = prod[i];
+
+            if top != 0 {
+                for (j, &cj) in c.iter().enumerate() {
+                    prod[i - 1 - j] = (prod[i - 1 - j] + top * cj) % p;
+                }
+            }
         }
 
-        cache.insert((self.0, k), res);
+        prod.truncate(d);
+        prod
+    };
 
-        res
+    let mut res = vec![0u64; d];
+    res[0] = 1;
+
+    // `x` itself, reduced in case `d == 1`.
+    let mut base = vec![0u64; d];
+    if d == 1 {
+        base[0] = c[0];
+    } else {
+        base[1] = 1;
+    }
+
+    let mut k = k;
+
+    while k > 0 {
+        if k & 1 == 1 {
+            res = mul(&res, &base);
+        }
+
+        base = mul(&base, &base);
+        k >>= 1;
+    }
+
+    res.iter().zip(s).fold(0, |acc, (&r, &si)| (acc + r * si) % p)
+}
+
+/// Parses the modulus for `Stones`, which must be positive.
+fn parse_modulus(arg: &str) -> Result<u128, &'static str> {
+    match arg.parse::<u128>() {
+        Ok(0) => Err("modulus must be positive"),
+        Ok(m) => Ok(m),
+        Err(_) => Err("expected a modulus"),
     }
 }
 
@@ -55,17 +535,166 @@ fn main() {
         .map(|n| Stone(n.parse().unwrap()))
         .collect::<Vec<_>>();
 
-    let mut cache = HashMap::new();
+    let mut counts = Stones::new(&stones, Rules::default(), None);
+
+    let generations = counts.simulate(K2).unwrap();
 
-    let res = stones.iter()
-        .map(|s| s.blink_k(K1, &mut cache))
-        .sum::<usize>();
+    println!("{}", generations[K1].total);
 
-    println!("{}", res);
+    println!("{}", generations[K2].total);
 
-    let res = stones.iter()
-        .map(|s| s.blink_k(K2, &mut cache))
-        .sum::<usize>();
+    // e.g., `cargo run --bin day11 -- 5000 1000000007 --rules rules.txt`, or
+    // `-- --steady 1000000000000 1000000007`
+    let mut args = std::env::args().skip(1).collect::<Vec<_>>();
 
-    println!("{}", res);
+    let rules = match args.iter().position(|arg| arg == "--rules") {
+        Some(i) => {
+            let path = args.get(i + 1).expect("expected a path to a rule spec").clone();
+            args.drain(i..=i + 1);
+
+            let spec = std::fs::read_to_string(path).unwrap();
+
+            Rules::try_from(spec.as_str()).unwrap()
+        },
+        None => Rules::default(),
+    };
+
+    if let Some(i) = args.iter().position(|arg| arg == "--steady") {
+        let k = args.get(i + 1).and_then(|k| k.parse::<u64>().ok())
+            .expect("expected the number of blinks");
+        let p = args.get(i + 2).and_then(|p| p.parse::<u64>().ok())
+            .expect("expected a prime modulus");
+
+        let graph = TransitionGraph::new(&stones, &rules, 1_000_000).unwrap();
+
+        println!(
+            "{} distinct values, closed after {} blinks",
+            graph.values.len(), graph.closed_after,
+        );
+
+        println!("{}", graph.count_mod(k, p).unwrap());
+
+        return;
+    }
+
+    if let Some(k) = args.first() {
+        let k = k.parse::<usize>().expect("expected the number of blinks");
+        let modulus = match args.get(1).map(|m| parse_modulus(m)).transpose() {
+            Ok(modulus) => modulus,
+            Err(e) => {
+                println!("error: {}", e);
+                return;
+            },
+        };
+
+        let mut counts = Stones::new(&stones, rules, modulus);
+
+        match counts.simulate(k) {
+            Ok(generations) => {
+                for generation in generations {
+                    println!("{} {} {}", generation.blinks, generation.total, generation.distinct);
+                }
+            },
+            Err(e) => println!("error after {} blinks: {}", counts.blinks, e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_default_rules() {
+        let rules = Rules::default();
+
+        assert_eq!(rules.apply(Stone(0)), Ok(vec![Stone(1)]));
+        assert_eq!(rules.apply(Stone(1000)), Ok(vec![Stone(10), Stone(0)]));
+        assert_eq!(rules.apply(Stone(99)), Ok(vec![Stone(9), Stone(9)]));
+        assert_eq!(rules.apply(Stone(125)), Ok(vec![Stone(253000)]));
+        assert!(rules.apply(Stone(u64::MAX / 1000)).is_err());
+
+        assert_eq!(Rules::try_from("0 -> 1\neven -> split\n* -> *2024"), Ok(rules));
+    }
+
+    #[test]
+    fn test_parse_rules() {
+        let rules = Rules::try_from("\
+# comment
+%3 -> split, +1
+7 -> 7, 7
+
+* -> *3").unwrap();
+
+        assert_eq!(rules.apply(Stone(123)), Ok(vec![Stone(12), Stone(3), Stone(124)]));
+        assert_eq!(rules.apply(Stone(7)), Ok(vec![Stone(7), Stone(7)]));
+        assert_eq!(rules.apply(Stone(5)), Ok(vec![Stone(15)]));
+
+        assert!(Rules::try_from("x -> 1").is_err());
+        assert!(Rules::try_from("1 => 1").is_err());
+
+        let mut counts = Stones::new(&[Stone(7)], rules, None);
+
+        assert_eq!(counts.simulate(3).unwrap()[3].total, 8);
+    }
+
+    #[test]
+    fn test_simulate() {
+        let mut counts = Stones::new(&[Stone(125), Stone(17)], Rules::default(), None);
+
+        let generations = counts.simulate(25).unwrap();
+
+        assert_eq!(generations[6].total, 22);
+        assert_eq!(generations[25].total, 55312);
+
+        let mut counts = Stones::new(&[Stone(125), Stone(17)], Rules::default(), Some(1000));
+
+        assert_eq!(counts.simulate(25).unwrap()[25].total, 312);
+    }
+
+    #[test]
+    fn test_parse_modulus() {
+        assert_eq!(parse_modulus("1000000007"), Ok(1000000007));
+        assert!(parse_modulus("0").is_err());
+        assert!(parse_modulus("-1").is_err());
+    }
+
+    #[test]
+    fn test_transition_graph() {
+        let stones = [Stone(125), Stone(17)];
+
+        let graph = TransitionGraph::new(&stones, &Rules::default(), 100_000).unwrap();
+
+        let p = 1_000_000_007;
+
+        assert_eq!(graph.count_mod(25, p), Ok(55312));
+
+        let mut counts = Stones::new(&stones, Rules::default(), Some(p as u128));
+
+        let generations = counts.simulate(200).unwrap();
+
+        for k in [0, 6, 75, 150, 200] {
+            assert_eq!(graph.count_mod(k, p), Ok(generations[k as usize].total as u64));
+        }
+
+        assert!(graph.count_mod(10, 1_000_000).is_err());
+    }
+
+    #[test]
+    fn test_transition_graph_never_closes() {
+        let rules = Rules::try_from("* -> +1").unwrap();
+
+        assert!(TransitionGraph::new(&[Stone(0)], &rules, 1000).is_err());
+    }
+
+    #[test]
+    fn test_linear_recurrence() {
+        // Fibonacci numbers.
+        let fib = [0, 1, 1, 2, 3, 5, 8, 13];
+
+        let c = berlekamp_massey(&fib, 1_000_000_007);
+
+        assert_eq!(c, vec![1, 1]);
+        assert_eq!(nth_term(&c, &fib, 90, 1_000_000_007), 2880067194370816120 % 1_000_000_007);
+    }
 }
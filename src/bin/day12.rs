@@ -1,4 +1,8 @@
-use std::collections::{HashMap, HashSet};
+use std::cmp::Reverse;
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+use aoc2024::Args;
 
 const INPUT: &str = include_str!("./day12.txt");
 
@@ -12,139 +16,635 @@ struct Garden {
 
 const DIRS: &[(i32, i32)] = &[(1, 0), (-1, 0), (0, 1), (0, -1)];
 
-impl Garden {
-    fn plots_in_a_same_region(
-        &self,
-        src: (usize, usize),
-        visited: &mut HashSet<(usize, usize)>
-    ) -> Vec<(i32, i32)> {
-        let mut res = vec![(src.0 as i32, src.1 as i32)];
-
-        visited.insert(src);
-
-        for &(dx, dy) in DIRS {
-            let x = src.0 as i32 + dx;
-            let y = src.1 as i32 + dy;
-
-            if x < 0 || x >= self.plots.len() as i32 ||
-                y < 0 || y >= self.plots[0].len() as i32 {
-                continue;
-            }
+const DIAGS: &[(i32, i32)] = &[
+    (1, 1),
+    (1, -1),
+    (-1, 1),
+    (-1, -1),
+];
 
-            let (x, y) = (x as usize, y as usize);
+impl Garden {
+    #[inline]
+    fn neighbor(&self, p: (usize, usize), d: (i32, i32)) -> Option<(usize, usize)> {
+        let x = p.0 as i32 + d.0;
+        let y = p.1 as i32 + d.1;
 
-            if self.plots[x][y] == self.plots[src.0][src.1] &&
-                !visited.contains(&(x, y)) {
-                res.extend(self.plots_in_a_same_region(
-                    (x as usize, y as usize),
-                    visited)
-                );
-            }
+        if x < 0 || x >= self.plots.len() as i32 ||
+            y < 0 || y >= self.plots[0].len() as i32 {
+            return None;
         }
 
-        res
+        Some((x as usize, y as usize))
     }
 
-    fn regions(&self) -> Vec<Region> {
-        let mut regions = Vec::new();
-        let mut visited: HashSet<(usize, usize)> = HashSet::new();
+    /// Labels each plot with the index of its region, flood-filling each
+    /// region with an explicit stack. Returns the labels and the number of
+    /// regions.
+    fn labels(&self) -> (Vec<Vec<usize>>, usize) {
+        let mut labels = vec![vec![usize::MAX; self.plots[0].len()]; self.plots.len()];
+        let mut num_regions = 0;
 
         for i in 0..self.plots.len() {
             for j in 0..self.plots[0].len() {
-                if visited.contains(&(i, j)) {
+                if labels[i][j] != usize::MAX {
                     continue;
                 }
 
-                regions.push(Region::new(
-                    self.plots_in_a_same_region((i, j), &mut visited)
-                ));
+                let mut stack = vec![(i, j)];
+                labels[i][j] = num_regions;
+
+                while let Some(p) = stack.pop() {
+                    for &d in DIRS {
+                        let Some(n) = self.neighbor(p, d) else {
+                            continue;
+                        };
+
+                        if labels[n.0][n.1] == usize::MAX &&
+                            self.plots[n.0][n.1] == self.plots[p.0][p.1] {
+                            labels[n.0][n.1] = num_regions;
+                            stack.push(n);
+                        }
+                    }
+                }
+
+                num_regions += 1;
+            }
+        }
+
+        (labels, num_regions)
+    }
+
+    /// Computes the area, perimeter and number of sides of every region in a
+    /// single pass over the labelled plots.
+    fn regions(&self) -> Vec<Region> {
+        let (labels, num_regions) = self.labels();
+
+        let mut regions = (0..num_regions)
+            .map(|_| Region {
+                plant: ' ',
+                min: (usize::MAX, usize::MAX),
+                max: (0, 0),
+                area: 0,
+                perimeter: 0,
+                sides: 0,
+            })
+            .collect::<Vec<_>>();
+
+        let same = |p: (usize, usize), d: (i32, i32)| {
+            self.neighbor(p, d).is_some_and(|n| labels[n.0][n.1] == labels[p.0][p.1])
+        };
+
+        for (i, row) in labels.iter().enumerate() {
+            for (j, &label) in row.iter().enumerate() {
+                let region = &mut regions[label];
+
+                region.plant = self.plots[i][j].0;
+                region.min = (region.min.0.min(i), region.min.1.min(j));
+                region.max = (region.max.0.max(i), region.max.1.max(j));
+                region.area += 1;
+
+                region.perimeter += DIRS.iter()
+                    .filter(|&&d| !same((i, j), d))
+                    .count() as u64;
+
+                // The number of sides equals the number of corners. Looking
+                // at each diagonal of a plot, there is a convex corner if
+                // neither orthogonal neighbor on that side is in the region,
+                // and a concave one if both are but the diagonal one isn't.
+                //
+                // Comparing labels (rather than plants) matters for enclaves
+                // like `day12-sample4.txt`, where the diagonal plot may grow
+                // the same plant but belong to a different region.
+                for &(dx, dy) in DIAGS {
+                    let p = same((i, j), (dx, 0));
+                    let q = same((i, j), (0, dy));
+                    let r = same((i, j), (dx, dy));
+
+                    if (!p && !q) || (p && q && !r) {
+                        region.sides += 1;
+                    }
+                }
             }
         }
 
         regions
     }
+
+    /// Traces the fence around each region (in the same order as
+    /// `regions`) as polygons.
+    ///
+    /// Each plot contributes a directed unit edge for each side that faces
+    /// another region, and the edges are then linked into loops. Where two
+    /// plots of a region only touch diagonally, the loop turns right so that
+    /// it keeps following the same plot.
+    fn boundaries(&self) -> Vec<Boundary> {
+        let (labels, num_regions) = self.labels();
+
+        let mut edges = Edges::new();
+
+        for (i, row) in labels.iter().enumerate() {
+            for (j, &label) in row.iter().enumerate() {
+                let (x, y) = (j as i64, i as i64);
+
+                // (direction to the neighbor, edge on that side)
+                let sides = [
+                    ((-1, 0), ((x, y), (x + 1, y))),
+                    ((0, 1), ((x + 1, y), (x + 1, y + 1))),
+                    ((1, 0), ((x + 1, y + 1), (x, y + 1))),
+                    ((0, -1), ((x, y + 1), (x, y))),
+                ];
+
+                for (d, (from, to)) in sides {
+                    let is_fence = self.neighbor((i, j), d)
+                        .is_none_or(|n| labels[n.0][n.1] != label);
+
+                    if is_fence {
+                        edges.entry((label, from)).or_default().push(to);
+                    }
+                }
+            }
+        }
+
+        let mut loops = vec![Vec::new(); num_regions];
+
+        // Following the rule above, every edge has exactly one successor
+        // (and one predecessor), so the edges split into disjoint loops.
+        while let Some(&(label, start)) = edges.keys().next() {
+            let first = take_edge(&mut edges, (label, start), None).unwrap();
+
+            let mut polygon = vec![start];
+            let mut prev = start;
+            let mut curr = first;
+
+            loop {
+                let heading = (curr.0 - prev.0, curr.1 - prev.1);
+                let right = (curr.0 - heading.1, curr.1 + heading.0);
+
+                let next = if curr == start && first == right {
+                    None
+                } else {
+                    take_edge(&mut edges, (label, curr), Some(right))
+                };
+
+                let Some(next) = next else {
+                    break;
+                };
+
+                polygon.push(curr);
+
+                prev = curr;
+                curr = next;
+            }
+
+            loops[label].push(corners(polygon));
+        }
+
+        loops.into_iter()
+            .map(|loops| {
+                let (outer, holes): (Vec<_>, Vec<_>) = loops.into_iter()
+                    .partition(|polygon| signed_area2(polygon) > 0);
+
+                Boundary {
+                    outer: outer.into_iter().next().unwrap(),
+                    holes,
+                }
+            })
+            .collect()
+    }
+
+    /// Renders the garden as SVG, with each region filled in a color derived
+    /// from its plant and outlined by its fence.
+    fn to_svg(&self, scale: u32) -> String {
+        let plants = self.regions().into_iter().map(|reg| reg.plant);
+
+        let mut svg = String::new();
+
+        let _ = writeln!(
+            svg,
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="{}" height="{}" viewBox="0 0 {} {}">"#,
+            self.plots[0].len() as u32 * scale,
+            self.plots.len() as u32 * scale,
+            self.plots[0].len(),
+            self.plots.len(),
+        );
+
+        for (boundary, plant) in self.boundaries().iter().zip(plants) {
+            let d = std::iter::once(&boundary.outer)
+                .chain(&boundary.holes)
+                .map(|polygon| {
+                    let points = polygon.iter()
+                        .map(|(x, y)| format!("{} {}", x, y))
+                        .collect::<Vec<_>>();
+
+                    format!("M {} Z", points.join(" L "))
+                })
+                .collect::<Vec<_>>()
+                .join(" ");
+
+            let _ = writeln!(
+                svg,
+                r#"  <path d="{}" fill="hsl({}, 60%, 70%)" fill-rule="evenodd" stroke="black" stroke-width="0.05"><title>{}</title></path>"#,
+                d,
+                (plant as u32 * 47) % 360,
+                xml_escape(&plant.to_string()),
+            );
+        }
+
+        svg.push_str("</svg>\n");
+
+        svg
+    }
+}
+
+/// Removes an edge starting at `key.1`, preferring the one ending at `to`.
+fn take_edge(
+    edges: &mut Edges,
+    key: (usize, (i64, i64)),
+    to: Option<(i64, i64)>,
+) -> Option<(i64, i64)> {
+    let ends = edges.get_mut(&key)?;
+    let k = ends.iter().position(|&end| Some(end) == to).unwrap_or(0);
+    let end = ends.swap_remove(k);
+
+    if ends.is_empty() {
+        edges.remove(&key);
+    }
+
+    Some(end)
+}
+
+/// Drops the vertices of `polygon` that lie in the middle of a straight edge.
+fn corners(polygon: Polygon) -> Polygon {
+    let n = polygon.len();
+
+    (0..n)
+        .filter(|&k| {
+            let (prev, curr, next) = (polygon[(k + n - 1) % n], polygon[k], polygon[(k + 1) % n]);
+
+            (curr.0 - prev.0, curr.1 - prev.1) != (next.0 - curr.0, next.1 - curr.1)
+        })
+        .map(|k| polygon[k])
+        .collect()
 }
 
 #[derive(Debug)]
 struct Region {
+    plant: char,
+    /// Top-left plot of the bounding box.
+    min: (usize, usize),
+    /// Bottom-right plot of the bounding box.
+    max: (usize, usize),
     area: u64,
     perimeter: u64,
     sides: u64,
 }
 
-const DIAGS: &[(i32, i32)] = &[
-    (1, 1),
-    (1, -1),
-    (-1, 1),
-    (-1, -1),
-];
-
 impl Region {
-    fn new(plots: Vec<(i32, i32)>) -> Self {
-        let mut fences: HashMap<(i32, i32), u64> = HashMap::new();
-
-        for &(x, y) in &plots {
-            for &(dx, dy) in DIRS {
-                let fx = (x as i32) + dx;
-                let fy = (y as i32) + dy;
-
-                if !plots.contains(&(fx, fy)) {
-                    fences.entry((fx, fy))
-                        .and_modify(|x| *x += 1)
-                        .or_insert(1);
-                }
-            }
+    fn price(&self) -> u64 {
+        self.area * self.perimeter
+    }
+
+    /// Price with the bulk discount, i.e., counting sides instead of fences.
+    fn bulk_price(&self) -> u64 {
+        self.area * self.sides
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum SortKey {
+    /// Region index, i.e., the order in which regions are found.
+    Id,
+    Plant,
+    Area,
+    Perimeter,
+    Sides,
+    Price,
+    BulkPrice,
+}
+
+impl TryFrom<&str> for SortKey {
+    type Error = &'static str;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        match value {
+            "id" => Ok(SortKey::Id),
+            "plant" => Ok(SortKey::Plant),
+            "area" => Ok(SortKey::Area),
+            "perimeter" => Ok(SortKey::Perimeter),
+            "sides" => Ok(SortKey::Sides),
+            "price" => Ok(SortKey::Price),
+            "bulk-price" => Ok(SortKey::BulkPrice),
+            _ => Err("unknown sort key"),
         }
+    }
+}
 
-        let mut corners = 0u64;
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Format {
+    Csv,
+    Json,
+}
 
-        for &(x, y) in &plots {
-            for &(dx, dy) in DIAGS {
-                let p = plots.contains(&(x as i32 + dx, y as i32));
-                let q = plots.contains(&(x as i32, y as i32 + dy));
-                let r = plots.contains(&(x as i32 + dx, y as i32 + dy));
+impl TryFrom<&str> for Format {
+    type Error = &'static str;
 
-                // The rationale behind addign the third term here is kinda
-                // hacky. It would help addressing cases like:
-                //
-                //     AAAAAA
-                //     AAABBA
-                //     AAABBA
-                //     ABBAAA
-                //     ABBAAA
-                //     AAAAAA
-                //
-                // TODO?: Add explanation
-                if (!r && p && q) || (!r && !p && !q) || (r && !p && !q) {
-                    corners += 1;
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        match value {
+            "csv" => Ok(Format::Csv),
+            "json" => Ok(Format::Json),
+            _ => Err("unknown format"),
+        }
+    }
+}
+
+/// `value` as XML text or attribute content, i.e., with `&`, `<`, `>` and
+/// `"` replaced by entities.
+fn xml_escape(value: &str) -> String {
+    value.chars()
+        .map(|c| match c {
+            '&' => String::from("&amp;"),
+            '<' => String::from("&lt;"),
+            '>' => String::from("&gt;"),
+            '"' => String::from("&quot;"),
+            c => c.to_string(),
+        })
+        .collect()
+}
+
+/// A CSV field holding `value`, quoted (with inner quotes doubled) if it
+/// contains a separator, a quote or a line break.
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// A JSON string literal holding `value`. Anything but printable ASCII is
+/// written as `\uXXXX` escapes (surrogate pairs beyond the BMP), so the
+/// output is plain ASCII.
+fn json_string(value: &str) -> String {
+    let mut out = String::from("\"");
+
+    for c in value.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            ' '..='~' => out.push(c),
+            _ => {
+                for unit in c.encode_utf16(&mut [0; 2]) {
+                    let _ = write!(out, "\\u{:04x}", unit);
                 }
-            }
+            },
         }
+    }
+
+    out.push('"');
+
+    out
+}
+
+/// Lists every region with its measurements, both prices and each price's
+/// share of the total. Numeric keys sort in descending order so that the
+/// regions that dominate the cost come first.
+fn report(regions: &[Region], key: SortKey, format: Format) -> String {
+    let total = regions.iter().map(Region::price).sum::<u64>().max(1) as f64;
+    let total_bulk = regions.iter().map(Region::bulk_price).sum::<u64>().max(1) as f64;
+
+    let mut rows = regions.iter().enumerate().collect::<Vec<_>>();
+
+    match key {
+        SortKey::Id => {},
+        SortKey::Plant => rows.sort_by_key(|&(_, reg)| reg.plant),
+        SortKey::Area => rows.sort_by_key(|&(_, reg)| Reverse(reg.area)),
+        SortKey::Perimeter => rows.sort_by_key(|&(_, reg)| Reverse(reg.perimeter)),
+        SortKey::Sides => rows.sort_by_key(|&(_, reg)| Reverse(reg.sides)),
+        SortKey::Price => rows.sort_by_key(|&(_, reg)| Reverse(reg.price())),
+        SortKey::BulkPrice => rows.sort_by_key(|&(_, reg)| Reverse(reg.bulk_price())),
+    }
+
+    let mut out = String::new();
+
+    match format {
+        Format::Csv => {
+            out.push_str("id,plant,min_row,min_col,max_row,max_col,area,perimeter,sides,price,price_share,bulk_price,bulk_price_share\n");
+
+            for (id, reg) in rows {
+                let _ = writeln!(
+                    out,
+                    "{},{},{},{},{},{},{},{},{},{},{:.4},{},{:.4}",
+                    id, csv_field(&reg.plant.to_string()), reg.min.0, reg.min.1, reg.max.0, reg.max.1,
+                    reg.area, reg.perimeter, reg.sides,
+                    reg.price(), reg.price() as f64 / total,
+                    reg.bulk_price(), reg.bulk_price() as f64 / total_bulk,
+                );
+            }
+        },
+        Format::Json => {
+            out.push_str("[\n");
+
+            let rows = rows.into_iter()
+                .map(|(id, reg)| format!(
+                    concat!(
+                        r#"  {{"id": {}, "plant": {}, "min": [{}, {}], "max": [{}, {}], "#,
+                        r#""area": {}, "perimeter": {}, "sides": {}, "#,
+                        r#""price": {}, "price_share": {:.4}, "#,
+                        r#""bulk_price": {}, "bulk_price_share": {:.4}}}"#,
+                    ),
+                    id, json_string(&reg.plant.to_string()), reg.min.0, reg.min.1, reg.max.0, reg.max.1,
+                    reg.area, reg.perimeter, reg.sides,
+                    reg.price(), reg.price() as f64 / total,
+                    reg.bulk_price(), reg.bulk_price() as f64 / total_bulk,
+                ))
+                .collect::<Vec<_>>();
+
+            out.push_str(&rows.join(",\n"));
+            out.push_str("\n]\n");
+        },
+    }
+
+    out
+}
+
+/// A closed polygon given by its corners, as lattice points `(x, y)` with
+/// `x` growing rightwards and `y` downwards (i.e., plot `(i, j)` spans
+/// `(j, i)..=(j + 1, i + 1)`). The region is always on the right-hand side
+/// of each edge, so outer boundaries run clockwise and holes
+/// counter-clockwise (as seen on screen).
+type Polygon = Vec<(i64, i64)>;
+
+/// The fence around a region.
+#[derive(Debug)]
+struct Boundary {
+    outer: Polygon,
+    /// Boundaries of enclaves of other regions.
+    holes: Vec<Polygon>,
+}
+
+/// Directed fence edges, keyed by region label and start vertex.
+type Edges = BTreeMap<(usize, (i64, i64)), Vec<(i64, i64)>>;
+
+/// Twice the signed area of `polygon`; positive if it runs clockwise.
+fn signed_area2(polygon: &Polygon) -> i64 {
+    polygon.iter()
+        .zip(polygon.iter().cycle().skip(1))
+        .map(|(&(x0, y0), &(x1, y1))| x0 * y1 - x1 * y0)
+        .sum()
+}
 
-        Region {
-            area: plots.len() as u64,
-            perimeter: fences.values().sum(),
-            sides: corners,
+impl From<&str> for Garden {
+    fn from(value: &str) -> Self {
+        Garden {
+            plots: value.lines()
+                .map(|row| row.chars().map(Plant).collect::<Vec<_>>())
+                .collect::<Vec<_>>()
         }
     }
 }
 
 fn main() {
-    let garden = Garden {
-        plots: INPUT.lines()
-            .map(|row| row.chars().map(Plant).collect::<Vec<_>>())
-            .collect::<Vec<_>>()
+    // e.g., `cargo run --bin day12 -- --svg garden.svg`, or
+    // `-- --input src/bin/day12-sample3.txt --report csv --sort bulk-price`
+    let options = ["--input", "--svg", "--report", "--sort"];
+
+    let args = match Args::parse(std::env::args().skip(1), &options) {
+        Ok(args) => args,
+        Err(e) => {
+            println!("error: {}", e);
+            return;
+        },
     };
 
+    let input = args.value("--input").map(|path| std::fs::read_to_string(path).unwrap());
+
+    let garden = Garden::from(input.as_deref().unwrap_or(INPUT));
+
     let regions = garden.regions();
 
     println!("{}", regions.iter()
-        .map(|reg| reg.area * reg.perimeter)
+        .map(Region::price)
         .sum::<u64>()
     );
 
     println!("{}", regions.iter()
-        .map(|reg| reg.area * reg.sides)
+        .map(Region::bulk_price)
         .sum::<u64>()
     );
+
+    if let Some(path) = args.value("--svg") {
+        std::fs::write(path, garden.to_svg(8)).unwrap();
+    }
+
+    if let Some(format) = args.value("--report") {
+        let format = Format::try_from(format).unwrap();
+        let key = args.value("--sort")
+            .map_or(Ok(SortKey::Price), SortKey::try_from)
+            .unwrap();
+
+        print!("{}", report(&regions, key, format));
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn prices(input: &str) -> (u64, u64) {
+        let garden = Garden::from(input);
+
+        let regions = garden.regions();
+
+        (
+            regions.iter().map(Region::price).sum(),
+            regions.iter().map(Region::bulk_price).sum(),
+        )
+    }
+
+    #[test]
+    fn test_samples() {
+        assert_eq!(prices(include_str!("./day12-sample.txt")), (140, 80));
+        assert_eq!(prices(include_str!("./day12-sample1.txt")), (772, 436));
+        assert_eq!(prices(include_str!("./day12-sample2.txt")).1, 236);
+        assert_eq!(prices(include_str!("./day12-sample3.txt")), (1930, 1206));
+        assert_eq!(prices(include_str!("./day12-sample4.txt")).1, 368);
+    }
+
+    #[test]
+    fn test_boundaries() {
+        let garden = Garden::from(include_str!("./day12-sample4.txt"));
+
+        let boundaries = garden.boundaries();
+
+        assert_eq!(boundaries[0].outer, vec![(0, 0), (6, 0), (6, 6), (0, 6)]);
+        assert_eq!(boundaries[0].holes.iter().map(|hole| hole.len()).sum::<usize>(), 8);
+        assert_eq!(boundaries[1].outer, vec![(3, 1), (5, 1), (5, 3), (3, 3)]);
+        assert!(boundaries[1].holes.is_empty());
+
+        for input in [
+            include_str!("./day12-sample1.txt"),
+            include_str!("./day12-sample3.txt"),
+            include_str!("./day12-sample4.txt"),
+        ] {
+            let garden = Garden::from(input);
+
+            for (region, boundary) in garden.regions().iter().zip(garden.boundaries()) {
+                let polygons = std::iter::once(&boundary.outer).chain(&boundary.holes);
+
+                // Each corner of the fence starts a new side.
+                assert_eq!(polygons.clone().map(|p| p.len() as u64).sum::<u64>(), region.sides);
+
+                // Holes are cut out of the outer boundary.
+                assert_eq!(
+                    polygons.map(signed_area2).sum::<i64>(),
+                    2 * region.area as i64,
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_report() {
+        let garden = Garden::from(include_str!("./day12-sample.txt"));
+
+        let regions = garden.regions();
+
+        assert_eq!(
+            report(&regions, SortKey::Price, Format::Csv),
+            "\
+id,plant,min_row,min_col,max_row,max_col,area,perimeter,sides,price,price_share,bulk_price,bulk_price_share
+0,A,0,0,0,3,4,10,4,40,0.2857,16,0.2000
+2,C,1,2,3,3,4,10,8,40,0.2857,32,0.4000
+1,B,1,0,2,1,4,8,4,32,0.2286,16,0.2000
+4,E,3,0,3,2,3,8,4,24,0.1714,12,0.1500
+3,D,1,3,1,3,1,4,4,4,0.0286,4,0.0500
+");
+
+        let json = report(&regions, SortKey::Id, Format::Json);
+
+        assert!(json.starts_with("[\n  {\"id\": 0, \"plant\": \"A\", \"min\": [0, 0], \"max\": [0, 3], "));
+        assert_eq!(json.lines().count(), regions.len() + 2);
+    }
+
+    #[test]
+    fn test_escaping() {
+        assert_eq!(csv_field("A"), "A");
+        assert_eq!(csv_field(","), "\",\"");
+        assert_eq!(csv_field("\""), "\"\"\"\"");
+
+        assert_eq!(json_string("A"), "\"A\"");
+        assert_eq!(json_string("\""), r#""\"""#);
+        assert_eq!(json_string("\\"), r#""\\""#);
+        assert_eq!(json_string("\t"), r#""\u0009""#);
+        assert_eq!(json_string("é"), r#""\u00e9""#);
+        assert_eq!(json_string("🌱"), r#""\ud83c\udf31""#);
+
+        assert_eq!(xml_escape("A"), "A");
+        assert_eq!(xml_escape(r#"<&>""#), "&lt;&amp;&gt;&quot;");
+
+        let svg = Garden::from("<&\n<<").to_svg(8);
+
+        assert!(svg.contains("<title>&lt;</title>"));
+        assert!(svg.contains("<title>&amp;</title>"));
+    }
 }
@@ -1,11 +1,104 @@
+use aoc2024::ext_gcd;
+
 const INPUT: &str = include_str!("./day13.txt");
 
 const OFFSET: i64 = 10000000000000;
 
-#[derive(Debug)]
-struct Buttons<const A: u64, const B: u64> {
-    a: (i64, i64),
-    b: (i64, i64),
+/// A button that moves the claw by `.0` and costs `.1` tokens per press.
+type Button = ((i64, i64), u64);
+
+fn div_floor(a: i128, b: i128) -> i128 {
+    let q = a / b;
+
+    if (a % b != 0) && ((a < 0) != (b < 0)) { q - 1 } else { q }
+}
+
+fn div_ceil(a: i128, b: i128) -> i128 {
+    -div_floor(-a, b)
+}
+
+/// The range `lo..=hi` of `t` such that `m + k * t >= 0` for every `(m, k)`
+/// in `constraints`, with `i128::MIN` and `i128::MAX` standing for no bound.
+fn range(constraints: &[(i128, i128)]) -> Option<(i128, i128)> {
+    let mut lo = i128::MIN;
+    let mut hi = i128::MAX;
+
+    for &(m, k) in constraints {
+        match k.signum() {
+            1 => lo = lo.max(div_ceil(-m, k)),
+            -1 => hi = hi.min(div_floor(m, -k)),
+            _ if m < 0 => return None,
+            _ => {},
+        }
+    }
+
+    (lo <= hi).then_some((lo, hi))
+}
+
+/// The solutions of `a * t ≡ b (mod m)`, for `m > 0`, as `t ≡ r (mod n)`.
+fn solve_congruence(a: i128, b: i128, m: i128) -> Result<Option<(i128, i128)>, &'static str> {
+    let (g, x, _) = ext_gcd(a, m);
+
+    if b % g != 0 {
+        return Ok(None);
+    }
+
+    let n = m / g;
+
+    let r = (b / g % n).checked_mul(x % n).ok_or("overflow")?.rem_euclid(n);
+
+    Ok(Some((r, n)))
+}
+
+/// Minimises `cost.0 * x + cost.1 * y` over the non-negative integer
+/// solutions of `a * x + b * y = c` (with `x, y <= limit`, if any).
+///
+/// All solutions are `x = x0 + (b / g) t`, `y = y0 - (a / g) t` for a
+/// particular one `(x0, y0)` from the extended Euclidean algorithm, and the
+/// cost is linear in `t`, so the optimum is at one end of the range of `t`
+/// that keeps both `x` and `y` non-negative.
+fn solve_1d(
+    a: i128,
+    b: i128,
+    c: i128,
+    cost: (i128, i128),
+    limit: Option<u64>,
+) -> Option<(i128, i128)> {
+    if a == 0 && b == 0 {
+        return (c == 0).then_some((0, 0));
+    }
+
+    let (g, x0, y0) = ext_gcd(a, b);
+
+    if c % g != 0 {
+        return None;
+    }
+
+    let (x0, y0) = (x0 * (c / g), y0 * (c / g));
+    let (dx, dy) = (b / g, -a / g);
+
+    let mut constraints = vec![(x0, dx), (y0, dy)];
+
+    if let Some(limit) = limit {
+        constraints.extend([(limit as i128 - x0, -dx), (limit as i128 - y0, -dy)]);
+    }
+
+    let (lo, hi) = range(&constraints)?;
+
+    let slope = cost.0 * dx + cost.1 * dy;
+
+    let t = if slope > 0 || (slope == 0 && lo != i128::MIN) {
+        lo
+    } else {
+        hi
+    };
+
+    // The cost is non-negative, so a decreasing direction is always bounded.
+    if t == i128::MIN || t == i128::MAX {
+        return if dx == 0 && dy == 0 { Some((x0, y0)) } else { None };
+    }
+
+    Some((x0 + dx * t, y0 + dy * t))
 }
 
 // a.0 * x + b.0 * y = prize.0
@@ -19,96 +112,605 @@ struct Buttons<const A: u64, const B: u64> {
 //
 // (x) =  (a.0 * b.1 - b.0 * a.1)^(-1) * (  b.1 -b.0 ) (prize.0)
 // (y)                                   ( -a.1  a.0 ) (prize.1)
+//
+// If the determinant is zero, the buttons move the claw along the same
+// line, so both equations say the same thing (or contradict each other) and
+// it boils down to `solve_1d`.
 
-impl<const A: u64, const B: u64> Buttons<A, B> {
-    fn solve(
-        &self,
-        prize: (i64, i64)
-    ) -> Option<u64> {
-        let d = self.a.0 * self.b.1 - self.b.0 * self.a.1;
+/// Returns the cheapest number of presses of buttons `a` and `b` (each
+/// pressed at most `limit` times, if any) that moves the claw to `prize`.
+fn solve2(
+    a: Button,
+    b: Button,
+    prize: (i64, i64),
+    limit: Option<u64>,
+) -> Option<(u64, u64)> {
+    let (a, cost_a) = ((a.0.0 as i128, a.0.1 as i128), a.1 as i128);
+    let (b, cost_b) = ((b.0.0 as i128, b.0.1 as i128), b.1 as i128);
+    let prize = (prize.0 as i128, prize.1 as i128);
 
-        let d_times_x = self.b.1 * prize.0 - self.b.0 * prize.1;
-        let d_times_y = -self.a.1 * prize.0 + self.a.0 * prize.1;
+    let d = a.0 * b.1 - b.0 * a.1;
 
-        if d_times_x % d == 0 && d_times_y % d == 0 {
-            let x = d_times_x / d;
-            let y = d_times_y / d;
+    let (x, y) = if d != 0 {
+        let d_times_x = b.1 * prize.0 - b.0 * prize.1;
+        let d_times_y = -a.1 * prize.0 + a.0 * prize.1;
 
-            if x >= 0 && y >= 0 {
-                return Some(A * (x as u64) + B * (y as u64));
-            }
+        if d_times_x % d != 0 || d_times_y % d != 0 {
+            return None;
+        }
+
+        (d_times_x / d, d_times_y / d)
+    } else if (a.0, b.0) != (0, 0) {
+        if a.1 * prize.0 != a.0 * prize.1 || b.1 * prize.0 != b.0 * prize.1 {
+            return None;
         }
 
-        None
+        solve_1d(a.0, b.0, prize.0, (cost_a, cost_b), limit)?
+    } else {
+        if prize.0 != 0 {
+            return None;
+        }
+
+        solve_1d(a.1, b.1, prize.1, (cost_a, cost_b), limit)?
+    };
+
+    let (x, y) = (u64::try_from(x).ok()?, u64::try_from(y).ok()?);
+
+    if limit.is_some_and(|limit| x > limit || y > limit) {
+        return None;
     }
+
+    Some((x, y))
+}
+
+/// Upper bound on the number of times `button` can be pressed without
+/// overshooting `prize` (along an axis no button moves the claw backwards
+/// on) or exceeding `limit`.
+fn max_presses(
+    buttons: &[Button],
+    button: Button,
+    prize: (i64, i64),
+    limit: Option<u64>,
+) -> Option<u64> {
+    [(button.0.0, prize.0, 0), (button.0.1, prize.1, 1)].into_iter()
+        .filter(|&(v, p, axis)| {
+            v > 0 && p >= 0 && buttons.iter().all(|b| if axis == 0 { b.0.0 >= 0 } else { b.0.1 >= 0 })
+        })
+        .map(|(v, p, _)| (p / v) as u64)
+        .chain(limit)
+        .min()
 }
 
-#[derive(Debug)]
-struct Eqn<const A: u64, const B: u64, const D: i64> {
-    buttons: Buttons<A, B>,
+/// Most combinations of presses `for_each_combination` tries before giving
+/// up.
+const MAX_COMBINATIONS: u128 = 1 << 24;
+
+/// Calls `f` with every combination of presses of `extra` (each bounded by
+/// `max_presses` over all of `buttons`) and what's left of `prize` after
+/// them.
+fn for_each_combination(
+    extra: &[Button],
+    buttons: &[Button],
     prize: (i64, i64),
+    limit: Option<u64>,
+    mut f: impl FnMut(&[u64], (i64, i64)) -> Result<(), &'static str>,
+) -> Result<(), &'static str> {
+    let bounds = extra.iter()
+        .map(|&button| max_presses(buttons, button, prize, limit))
+        .collect::<Option<Vec<_>>>()
+        .ok_or("can't bound the number of presses")?;
+
+    let combinations = bounds.iter()
+        .try_fold(1u128, |n, &bound| n.checked_mul(bound as u128 + 1));
+
+    if combinations.is_none_or(|n| n > MAX_COMBINATIONS) {
+        return Err("too many combinations of presses to try");
+    }
+
+    let mut presses = vec![0u64; extra.len()];
+
+    loop {
+        let rest = extra.iter().zip(&presses)
+            .fold((prize.0 as i128, prize.1 as i128), |p, (button, &n)| {
+                (p.0 - button.0.0 as i128 * n as i128, p.1 - button.0.1 as i128 * n as i128)
+            });
+
+        let rest = (
+            i64::try_from(rest.0).map_err(|_| "overflow")?,
+            i64::try_from(rest.1).map_err(|_| "overflow")?,
+        );
+
+        f(&presses, rest)?;
+
+        // Next combination, odometer-style.
+        let Some(i) = (0..presses.len()).find(|&i| presses[i] < bounds[i]) else {
+            return Ok(());
+        };
+
+        presses[i] += 1;
+        presses[..i].iter_mut().for_each(|n| *n = 0);
+    }
 }
 
-impl<const A: u64, const B: u64, const D: i64> Eqn<A, B, D> {
-    fn solve(&self) -> Option<u64> {
-        self.buttons.solve(self.prize)
+/// Total cost of pressing each of `buttons` as many times as in `presses`.
+fn total_cost(buttons: &[Button], presses: &[u64]) -> Result<u64, &'static str> {
+    let cost = presses.iter().zip(buttons)
+        .try_fold(0u128, |sum, (&n, b)| sum.checked_add((n as u128).checked_mul(b.1 as u128)?))
+        .ok_or("overflow")?;
+
+    u64::try_from(cost).map_err(|_| "cost overflows u64")
+}
+
+// Pressing `c` t times leaves
+//
+// a.0 * x + b.0 * y = prize.0 - c.0 * t
+// a.1 * x + b.1 * y = prize.1 - c.1 * t
+//
+// which, as in `solve2`, gives
+//
+// x = ((b.1 * prize.0 - b.0 * prize.1) - (b.1 * c.0 - b.0 * c.1) * t) / d
+// y = ((a.0 * prize.1 - a.1 * prize.0) - (a.0 * c.1 - a.1 * c.0) * t) / d
+//
+// Both are integers for `t` in a residue class modulo some divisor of `d`,
+// and non-negative (and within the limit) for `t` in some range, and the
+// cost is linear in `t`, so the cheapest `t` is one of the two ends of that
+// range that fall in the class.
+
+/// Returns the cheapest number of presses of buttons `a`, `b` and `c` (each
+/// pressed at most `limit` times, if any) that moves the claw to `prize`.
+/// `a` and `b` must not move the claw along the same line.
+fn solve3(
+    a: Button,
+    b: Button,
+    c: Button,
+    prize: (i64, i64),
+    limit: Option<u64>,
+) -> Result<Option<[u64; 3]>, &'static str> {
+    let (a, cost_a) = ((a.0.0 as i128, a.0.1 as i128), a.1 as i128);
+    let (b, cost_b) = ((b.0.0 as i128, b.0.1 as i128), b.1 as i128);
+    let (c, cost_c) = ((c.0.0 as i128, c.0.1 as i128), c.1 as i128);
+    let prize = (prize.0 as i128, prize.1 as i128);
+
+    let d = a.0 * b.1 - b.0 * a.1;
+
+    assert_ne!(d, 0, "a and b are collinear");
+
+    let (x0, xt) = (b.1 * prize.0 - b.0 * prize.1, b.1 * c.0 - b.0 * c.1);
+    let (y0, yt) = (a.0 * prize.1 - a.1 * prize.0, a.0 * c.1 - a.1 * c.0);
+
+    let Some((rx, mx)) = solve_congruence(xt, x0, d.abs())? else {
+        return Ok(None);
+    };
+
+    let Some((ry, my)) = solve_congruence(yt, y0, d.abs())? else {
+        return Ok(None);
+    };
+
+    // t = rx + mx * k with t ≡ ry (mod my)
+    let Some((k, mk)) = solve_congruence(mx, ry - rx, my)? else {
+        return Ok(None);
+    };
+
+    let m = mx.checked_mul(mk).ok_or("overflow")?;
+    let r = (rx + mx * k).rem_euclid(m);
+
+    // Multiplied by `d.signum()`, so that `d` can be taken as positive.
+    let (s, d) = (d.signum(), d.abs());
+
+    let mut constraints = vec![(s * x0, -s * xt), (s * y0, -s * yt), (0, 1)];
+
+    if let Some(limit) = limit {
+        let scaled = (limit as i128).checked_mul(d).ok_or("overflow")?;
+
+        constraints.extend([(scaled - s * x0, s * xt), (scaled - s * y0, s * yt), (limit as i128, -1)]);
     }
+
+    let Some((lo, hi)) = range(&constraints) else {
+        return Ok(None);
+    };
+
+    // `lo` is finite, since `t >= 0`.
+    let lo = r + m * div_ceil(lo - r, m);
+    let hi = if hi == i128::MAX { hi } else { r + m * div_floor(hi - r, m) };
+
+    if lo > hi {
+        return Ok(None);
+    }
+
+    // `d` times the change in cost for each press of `c`. The cost is
+    // non-negative, so it can't keep decreasing as `t` grows.
+    let slope = cost_c * d - s * (cost_a * xt + cost_b * yt);
+
+    let t = if slope >= 0 || hi == i128::MAX { lo } else { hi };
+
+    let solve_for = |n0: i128, nt: i128| {
+        let n = t.checked_mul(nt).and_then(|n| n0.checked_sub(n)).ok_or("overflow")? * s / d;
+
+        u64::try_from(n).map_err(|_| "overflow")
+    };
+
+    Ok(Some([solve_for(x0, xt)?, solve_for(y0, yt)?, u64::try_from(t).map_err(|_| "overflow")?]))
 }
 
-impl<const A: u64, const B: u64, const D: i64> TryFrom<&str> for Eqn<A, B, D> {
-    type Error = &'static str;
+/// Returns the cheapest presses of each button (and the total cost) that
+/// move the claw to `prize`, pressing each button at most `limit` times.
+///
+/// With more than two buttons this is a small integer linear program. Three
+/// buttons are solved exactly by `solve3`, unless they all move the claw
+/// along the same line. Any other buttons are enumerated by
+/// `for_each_combination`, so there must be few enough presses of them to
+/// try.
+fn solve(
+    buttons: &[Button],
+    prize: (i64, i64),
+    limit: Option<u64>,
+) -> Result<Option<(Vec<u64>, u64)>, &'static str> {
+    let det = |a: Button, b: Button| a.0.0 as i128 * b.0.1 as i128 - b.0.0 as i128 * a.0.1 as i128;
 
-    fn try_from(value: &str) -> Result<Self, Self::Error> {
-        let mut a = (0i64, 0i64);
-        let mut b = (0i64, 0i64);
-        let mut prize = (0i64, 0i64);
+    let with_cost = |presses: Vec<u64>| {
+        let cost = total_cost(buttons, &presses)?;
+
+        Ok((presses, cost))
+    };
+
+    let mut best: Option<(Vec<u64>, u64)> = None;
+
+    let mut consider = |presses: Vec<u64>| {
+        let (presses, cost) = with_cost(presses)?;
+
+        if best.as_ref().is_none_or(|(_, best)| cost < *best) {
+            best = Some((presses, cost));
+        }
+
+        Ok(())
+    };
+
+    match buttons {
+        [] => return Ok((prize == (0, 0)).then_some((vec![], 0))),
+        [a] => {
+            let b = ((0, 0), 0);
 
-        value.lines().enumerate().for_each(|(i, line)| {
-            if i < 2 {
-                let parts = line.split_whitespace().collect::<Vec<_>>();
+            return solve2(*a, b, prize, limit).map(|(x, _)| with_cost(vec![x])).transpose();
+        },
+        [a, b] => return solve2(*a, *b, prize, limit).map(|(x, y)| with_cost(vec![x, y])).transpose(),
+        [a, b, c, extra @ ..] => {
+            // Two of the first three buttons that aren't collinear, and the
+            // third one.
+            let order = [[0, 1, 2], [0, 2, 1], [1, 2, 0]].into_iter()
+                .find(|&[i, j, _]| det(buttons[i], buttons[j]) != 0);
 
-                let p = (
-                    parts[2][2..(parts[2].len() - 1)].parse().unwrap(),
-                    parts[3][2..parts[3].len()].parse().unwrap(),
-                );
+            for_each_combination(extra, buttons, prize, limit, |presses, rest| {
+                let first = match order {
+                    Some([i, j, k]) => {
+                        solve3(buttons[i], buttons[j], buttons[k], rest, limit)?.map(|n| {
+                            let mut first = [0; 3];
 
-                if i == 0 { a = p; } else { b = p; }
-            } else {
-                let parts = line.split_whitespace().collect::<Vec<_>>();
+                            (first[i], first[j], first[k]) = (n[0], n[1], n[2]);
 
-                prize.0 = parts[1][2..(parts[1].len() - 1)].parse().unwrap();
-                prize.1 = parts[2][2..parts[2].len()].parse().unwrap();
+                            vec![first]
+                        })
+                    },
+                    None => {
+                        let mut found = Vec::new();
 
-                prize.0 += D;
-                prize.1 += D;
+                        for_each_combination(&[*c], buttons, rest, limit, |z, rest| {
+                            found.extend(solve2(*a, *b, rest, limit).map(|(x, y)| [x, y, z[0]]));
+
+                            Ok(())
+                        })?;
+
+                        Some(found)
+                    },
+                };
+
+                for first in first.into_iter().flatten() {
+                    consider(first.into_iter().chain(presses.iter().copied()).collect())?;
+                }
+
+                Ok(())
+            })?;
+        },
+    }
+
+    Ok(best)
+}
+
+/// Token costs and prize offset, which differ between the two parts.
+#[derive(Debug, Clone, Copy)]
+struct Config {
+    cost_a: u64,
+    cost_b: u64,
+    /// Added to both coordinates of every prize.
+    offset: i64,
+    /// Maximum number of presses of each button.
+    max_presses: Option<u64>,
+}
+
+#[derive(Debug, PartialEq)]
+struct Machine {
+    a: (i64, i64),
+    b: (i64, i64),
+    prize: (i64, i64),
+}
+
+#[derive(Debug, PartialEq)]
+struct Solution {
+    presses_a: u64,
+    presses_b: u64,
+    tokens: u64,
+}
+
+impl Machine {
+    /// Returns an error if the prize or the cost overflows.
+    fn solve(&self, config: &Config) -> Result<Option<Solution>, &'static str> {
+        let prize = (
+            self.prize.0.checked_add(config.offset).ok_or("prize overflows i64")?,
+            self.prize.1.checked_add(config.offset).ok_or("prize overflows i64")?,
+        );
+
+        let buttons = [(self.a, config.cost_a), (self.b, config.cost_b)];
+
+        Ok(solve(&buttons, prize, config.max_presses)?
+            .map(|(presses, tokens)| Solution {
+                presses_a: presses[0],
+                presses_b: presses[1],
+                tokens,
+            }))
+    }
+}
+
+/// Parses `X+94, Y+34` or `X=8400, Y=5400`, in either order and with either
+/// sign.
+fn parse_xy(value: &str) -> Result<(i64, i64), &'static str> {
+    let mut x = None;
+    let mut y = None;
+
+    for part in value.split(',') {
+        let part = part.trim();
+
+        let mut chars = part.chars();
+
+        let axis = match chars.next() {
+            Some('X' | 'x') => &mut x,
+            Some('Y' | 'y') => &mut y,
+            _ => return Err("expected X or Y"),
+        };
+
+        let n = chars.as_str().trim();
+        let n = n.strip_prefix('=').unwrap_or(n).trim();
+
+        if axis.replace(n.parse::<i64>().map_err(|_| "invalid number")?).is_some() {
+            return Err("duplicate coordinate");
+        }
+    }
+
+    Ok((x.ok_or("missing X")?, y.ok_or("missing Y")?))
+}
+
+impl TryFrom<&str> for Machine {
+    type Error = &'static str;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        let mut a = None;
+        let mut b = None;
+        let mut prize = None;
+
+        for line in value.lines().map(str::trim).filter(|line| !line.is_empty()) {
+            let (label, xy) = line.split_once(':').ok_or("missing ':'")?;
+
+            let field = match label.trim() {
+                "Button A" => &mut a,
+                "Button B" => &mut b,
+                "Prize" => &mut prize,
+                _ => return Err("unknown line"),
+            };
+
+            if field.replace(parse_xy(xy)?).is_some() {
+                return Err("duplicate line");
             }
-        });
+        }
 
-        Ok(Eqn {
-            buttons: Buttons { a, b },
-            prize,
+        Ok(Machine {
+            a: a.ok_or("missing button A")?,
+            b: b.ok_or("missing button B")?,
+            prize: prize.ok_or("missing prize")?,
         })
     }
 }
 
 fn main() {
-    let part1 = INPUT.split("\n\n")
-        .filter_map(|s| {
-            let eqn: Eqn<3, 1, 0> = s.try_into().unwrap();
+    let machines = INPUT.split("\n\n")
+        .map(|s| Machine::try_from(s).unwrap())
+        .collect::<Vec<_>>();
 
-            eqn.solve()
-        })
-        .sum::<u64>();
+    let part1 = Config { cost_a: 3, cost_b: 1, offset: 0, max_presses: Some(100) };
 
-    println!("{}", part1);
+    println!("{}", machines.iter()
+        .filter_map(|machine| machine.solve(&part1).unwrap())
+        .map(|solution| solution.tokens)
+        .sum::<u64>()
+    );
 
-    let part2 = INPUT.split("\n\n")
-        .filter_map(|s| {
-            let eqn: Eqn<3, 1, OFFSET> = s.try_into().unwrap();
+    let part2 = Config { cost_a: 3, cost_b: 1, offset: OFFSET, max_presses: None };
 
-            eqn.solve()
-        })
-        .sum::<u64>();
+    println!("{}", machines.iter()
+        .filter_map(|machine| machine.solve(&part2).unwrap())
+        .map(|solution| solution.tokens)
+        .sum::<u64>()
+    );
+
+    // e.g., `cargo run --bin day13 -- --cost-a 1 --cost-b 3 --offset 0 --limit 50`
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+
+    if args.is_empty() {
+        return;
+    }
+
+    let mut config = part1;
+    config.max_presses = None;
+
+    for arg in args.chunks(2) {
+        let value = arg.get(1).map(|value| value.as_str()).unwrap_or_default();
+
+        match arg[0].as_str() {
+            "--cost-a" => config.cost_a = value.parse().unwrap(),
+            "--cost-b" => config.cost_b = value.parse().unwrap(),
+            "--offset" => config.offset = value.parse().unwrap(),
+            "--limit" => config.max_presses = Some(value.parse().unwrap()),
+            _ => panic!("unknown option"),
+        }
+    }
 
-    println!("{}", part2);
-}
\ No newline at end of file
+    for (i, machine) in machines.iter().enumerate() {
+        match machine.solve(&config) {
+            Ok(Some(Solution { presses_a, presses_b, tokens })) => {
+                println!("{}: a={} b={} tokens={}", i, presses_a, presses_b, tokens);
+            },
+            Ok(None) => println!("{}: -", i),
+            Err(e) => println!("{}: error: {}", i, e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_solve2() {
+        assert_eq!(solve2(((94, 34), 3), ((22, 67), 1), (8400, 5400), None), Some((80, 40)));
+        assert_eq!(solve2(((26, 66), 3), ((67, 21), 1), (12748, 12176), None), None);
+        assert_eq!(solve2(((94, 34), 3), ((22, 67), 1), (8400, 5400), Some(50)), None);
+    }
+
+    #[test]
+    fn test_collinear_buttons() {
+        // B is cheaper per unit of distance, but 7 can't be reached with B
+        // alone.
+        assert_eq!(solve2(((1, 1), 3), ((2, 2), 1), (7, 7), None), Some((1, 3)));
+
+        // A is cheaper per unit of distance.
+        assert_eq!(solve2(((3, 6), 1), ((1, 2), 1), (10, 20), None), Some((3, 1)));
+
+        assert_eq!(solve2(((2, 2), 3), ((4, 4), 1), (7, 7), None), None);
+        assert_eq!(solve2(((1, 1), 3), ((2, 2), 1), (7, 8), None), None);
+        assert_eq!(solve2(((0, 1), 3), ((0, 2), 1), (0, 5), None), Some((1, 2)));
+        assert_eq!(solve2(((0, 0), 3), ((0, 0), 1), (0, 0), None), Some((0, 0)));
+    }
+
+    #[test]
+    fn test_more_buttons() {
+        let buttons = [((94, 34), 3), ((22, 67), 1), ((1, 1), 1)];
+
+        let (presses, tokens) = solve(&buttons, (8400, 5400), None).unwrap().unwrap();
+
+        assert!(tokens <= 280);
+        assert_eq!(
+            presses.iter().zip(&buttons)
+                .fold((0, 0), |p, (&n, b)| (p.0 + b.0.0 * n as i64, p.1 + b.0.1 * n as i64)),
+            (8400, 5400),
+        );
+
+        // Exact, even though the third button moves the claw backwards.
+        assert_eq!(
+            solve(&[((1, 0), 1), ((0, 1), 1), ((-1, -1), 1)], (5, 5), None),
+            Ok(Some((vec![5, 5, 0], 10))),
+        );
+
+        // A fourth button has to be enumerated.
+        assert!(solve(&[((1, 0), 1), ((0, 1), 1), ((1, 1), 1), ((-1, -1), 1)], (5, 5), None).is_err());
+        assert!(solve(&[((1, 0), 1), ((0, 1), 1), ((1, 1), 1), ((1, 2), 1)], (OFFSET, OFFSET), None).is_err());
+    }
+
+    #[test]
+    fn test_solve3() {
+        let buttons = [((3, 1), 3), ((1, 2), 1), ((2, 3), 2)];
+
+        // Against every combination of presses.
+        for limit in [None, Some(4)] {
+            for prize in (0..30).flat_map(|x| (0..30).map(move |y| (x, y))) {
+                let mut best = None;
+
+                for n in (0..=10).flat_map(|x| (0..=15).flat_map(move |y| (0..=10).map(move |z| [x, y, z]))) {
+                    if limit.is_some_and(|limit| n.iter().any(|&n| n > limit)) {
+                        continue;
+                    }
+
+                    let p = n.iter().zip(&buttons)
+                        .fold((0, 0), |p, (&n, b)| (p.0 + b.0.0 * n as i64, p.1 + b.0.1 * n as i64));
+
+                    let cost = total_cost(&buttons, &n).unwrap();
+
+                    if p == prize && best.is_none_or(|best| cost < best) {
+                        best = Some(cost);
+                    }
+                }
+
+                assert_eq!(solve(&buttons, prize, limit).unwrap().map(|(_, cost)| cost), best);
+            }
+        }
+
+        // Collinear buttons are enumerated.
+        assert_eq!(solve(&[((1, 1), 3), ((2, 2), 1), ((3, 3), 1)], (7, 7), None), Ok(Some((vec![0, 2, 1], 3))));
+    }
+
+    #[test]
+    fn test_solve3_offset() {
+        let buttons = [((26, 66), 3), ((67, 21), 1), ((1, 1), 1)];
+        let prize = (12748 + OFFSET, 12176 + OFFSET);
+
+        let (presses, tokens) = solve(&buttons, prize, None).unwrap().unwrap();
+
+        assert_eq!(
+            presses.iter().zip(&buttons)
+                .fold((0, 0), |p, (&n, b)| (p.0 + b.0.0 * n as i64, p.1 + b.0.1 * n as i64)),
+            prize,
+        );
+
+        let (x, y) = solve2(buttons[0], buttons[1], prize, None).unwrap();
+
+        assert!(tokens <= 3 * x + y);
+    }
+
+    #[test]
+    fn test_collinear_buttons_with_limit() {
+        // Without a limit, it'd be 1 press of A and 3 of B.
+        assert_eq!(solve2(((1, 1), 3), ((2, 2), 1), (7, 7), Some(2)), None);
+        assert_eq!(solve2(((1, 1), 3), ((2, 2), 1), (9, 9), Some(3)), Some((3, 3)));
+    }
+
+    #[test]
+    fn test_parse_machine() {
+        let machine = Machine::try_from("\
+Button A: X+94, Y+34
+Button B: X+22, Y+67
+Prize: X=8400, Y=5400").unwrap();
+
+        assert_eq!(machine, Machine { a: (94, 34), b: (22, 67), prize: (8400, 5400) });
+
+        let config = Config { cost_a: 3, cost_b: 1, offset: 0, max_presses: Some(100) };
+
+        assert_eq!(
+            machine.solve(&config),
+            Ok(Some(Solution { presses_a: 80, presses_b: 40, tokens: 280 })),
+        );
+
+        let config = Config { cost_a: u64::MAX, ..config };
+
+        assert_eq!(machine.solve(&config), Err("cost overflows u64"));
+
+        let config = Config { offset: i64::MAX, ..config };
+
+        assert_eq!(machine.solve(&config), Err("prize overflows i64"));
+
+        let machine = Machine::try_from("\
+Prize: Y=-5400, X=8400
+Button B: Y-67,X+22
+Button A:  X = 94 , Y-34").unwrap();
+
+        assert_eq!(machine, Machine { a: (94, -34), b: (22, -67), prize: (8400, -5400) });
+
+        assert!(Machine::try_from("Button A: X+1, Y+1\nPrize: X=1, Y=1").is_err());
+        assert!(Machine::try_from("Button A: X+1, X+1").is_err());
+    }
+}
@@ -1,50 +1,56 @@
-use std::{cmp::Ordering, collections::HashMap};
+use std::{io::BufRead, ops::Range};
+
+use aoc2024::{ext_gcd, Args};
 
 const INPUT: &str = include_str!("./day14.txt");
 
 #[derive(Clone, Copy, PartialEq, Eq, Debug)]
-struct Robot<const W: usize, const H: usize> {
+struct Robot {
     p: (i64, i64),
     v: (i64, i64),
 }
 
-impl<const W: usize, const H: usize> Robot<W, H> {
+impl Robot {
     #[allow(dead_code)]
-    fn update(&mut self) {
-        self.p = (
-            (self.p.0 + self.v.0).rem_euclid(W as i64),
-            (self.p.1 + self.v.1).rem_euclid(H as i64)
-        );
+    fn update(&mut self, (width, height): (usize, usize)) {
+        self.update_n(1, (width, height));
     }
 
-    fn update_n(&mut self, n: i64) {
+    fn update_n(&mut self, n: i64, (width, height): (usize, usize)) {
         self.p = (
-            (self.p.0 + n * self.v.0).rem_euclid(W as i64),
-            (self.p.1 + n * self.v.1).rem_euclid(H as i64)
+            (self.p.0 + n * self.v.0).rem_euclid(width as i64),
+            (self.p.1 + n * self.v.1).rem_euclid(height as i64)
         );
     }
 
-    // For convenience, assign each quadrant with id:
-    // +----->
-    // | 1 2
-    // | 3 4
-    // v
-    // NOTE: This function assumes W and H are both odd numbers.
-    fn quadrant(&self) -> Option<u8> {
-        let mw = (W as i64) / 2;
-        let mh = (H as i64) / 2;
-
-        match (self.p.0.cmp(&mw), self.p.1.cmp(&mh)) {
-            (Ordering::Less, Ordering::Less) => Some(1),
-            (Ordering::Greater, Ordering::Less) => Some(2),
-            (Ordering::Less, Ordering::Greater) => Some(3),
-            (Ordering::Greater, Ordering::Greater) => Some(4),
-            _ => None
-        }
+    /// Returns the `(column, row)` of the `cols` by `rows` partition of a
+    /// `width` by `height` room that this robot is in, numbered from the top
+    /// left. A robot on a tile that straddles a dividing line is in none.
+    fn cell(
+        &self,
+        (width, height): (usize, usize),
+        (cols, rows): (usize, usize),
+    ) -> Option<(usize, usize)> {
+        Some((
+            band(self.p.0 as usize, width, cols)?,
+            band(self.p.1 as usize, height, rows)?,
+        ))
     }
 }
 
-impl<const W: usize, const H: usize> TryFrom<&str> for Robot<W, H> {
+/// Splits `0..len` into `parts` equally long bands and returns the band tile
+/// `x` (which covers `x..x + 1`) lies in, or `None` if a boundary between two
+/// bands runs through the tile.
+///
+/// With two parts, this excludes the middle tile of an odd length (the
+/// puzzle's quadrants), while an even length splits cleanly between tiles.
+fn band(x: usize, len: usize, parts: usize) -> Option<usize> {
+    let i = x * parts / len;
+
+    ((x + 1) * parts <= (i + 1) * len).then_some(i)
+}
+
+impl TryFrom<&str> for Robot {
     type Error = &'static str;
 
     fn try_from(value: &str) -> Result<Self, Self::Error> {
@@ -70,27 +76,354 @@ impl<const W: usize, const H: usize> TryFrom<&str> for Robot<W, H> {
     }
 }
 
-#[allow(dead_code)]
 #[derive(Clone)]
-struct Robots<const W: usize, const H: usize>(Vec<Robot<W, H>>);
+struct Robots {
+    width: usize,
+    height: usize,
+    robots: Vec<Robot>,
+}
+
+/// How `Robots::find_picture` tells a picture apart from noise.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Score {
+    /// Variance of the x and y coordinates. Robots gather in one place when
+    /// they form a picture, so both variances drop sharply.
+    Variance,
+    /// Size of the largest 4-connected cluster of robots. A picture is drawn
+    /// with adjacent robots, so it's the frame with the largest cluster.
+    LargestCluster,
+}
+
+/// Finds the `t` in `0..lcm(m1, m2)` with `t = a1 (mod m1)` and
+/// `t = a2 (mod m2)`, if any (Chinese remainder theorem).
+fn crt(a1: i64, m1: i64, a2: i64, m2: i64) -> Option<i64> {
+    let (g, p, _) = ext_gcd(m1 as i128, m2 as i128);
+    let (g, p) = (g as i64, p as i64);
+
+    if (a2 - a1) % g != 0 {
+        return None;
+    }
+
+    let lcm = m1 / g * m2;
+
+    // t = a1 + m1 * k, where m1 * k = a2 - a1 (mod m2)
+    let k = ((a2 - a1) / g * p).rem_euclid(m2 / g);
+
+    Some((a1 + m1 * k).rem_euclid(lcm))
+}
+
+fn variance(xs: impl Iterator<Item = i64> + Clone) -> f64 {
+    let n = xs.clone().count().max(1) as f64;
+    let mean = xs.clone().sum::<i64>() as f64 / n;
+
+    xs.map(|x| (x as f64 - mean).powi(2)).sum::<f64>() / n
+}
+
+impl Robots {
+    fn new(input: &str, width: usize, height: usize) -> Result<Self, &'static str> {
+        if width == 0 || height == 0 {
+            return Err("room must not be empty");
+        }
 
-#[allow(dead_code)]
-impl<const W: usize, const H: usize> Robots<W, H> {
+        let robots = input.lines()
+            .map(Robot::try_from)
+            .collect::<Result<Vec<_>, _>>()?;
+
+        if robots.iter().any(|robot| {
+            !(0..width as i64).contains(&robot.p.0) || !(0..height as i64).contains(&robot.p.1)
+        }) {
+            return Err("robot outside of the room");
+        }
+
+        Ok(Robots { width, height, robots })
+    }
+
+    fn size(&self) -> (usize, usize) {
+        (self.width, self.height)
+    }
+
+    #[allow(dead_code)]
     fn update(&mut self) {
-        self.0.iter_mut().for_each(|robot| robot.update());
+        self.update_n(1);
+    }
+
+    fn update_n(&mut self, n: i64) {
+        let size = self.size();
+
+        self.robots.iter_mut().for_each(|robot| robot.update_n(n, size));
+    }
+
+    /// Number of robots in each cell of a `cols` by `rows` partition of the
+    /// room, row by row. See `Robot::cell`.
+    fn partition(&self, cols: usize, rows: usize) -> Vec<Vec<usize>> {
+        let mut counts = vec![vec![0; cols]; rows];
+
+        for robot in &self.robots {
+            if let Some((col, row)) = robot.cell(self.size(), (cols, rows)) {
+                counts[row][col] += 1;
+            }
+        }
+
+        counts
+    }
+
+    /// The product of the robot counts of the cells of a `cols` by `rows`
+    /// partition; the puzzle's safety factor is the 2 by 2 case.
+    fn safety_factor(&self, cols: usize, rows: usize) -> usize {
+        self.partition(cols, rows).iter().flatten().product()
+    }
+
+    fn variances(&self) -> (f64, f64) {
+        (
+            variance(self.robots.iter().map(|robot| robot.p.0)),
+            variance(self.robots.iter().map(|robot| robot.p.1)),
+        )
+    }
+
+    fn largest_cluster(&self) -> usize {
+        let mut occupied = vec![vec![false; self.width]; self.height];
+
+        for robot in &self.robots {
+            occupied[robot.p.1 as usize][robot.p.0 as usize] = true;
+        }
+
+        let mut largest = 0;
+
+        for y in 0..self.height {
+            for x in 0..self.width {
+                if !occupied[y][x] {
+                    continue;
+                }
+
+                occupied[y][x] = false;
+
+                let mut stack = vec![(x, y)];
+                let mut size = 0;
+
+                while let Some((x, y)) = stack.pop() {
+                    size += 1;
+
+                    let neighbors = [
+                        (x.wrapping_sub(1), y),
+                        (x + 1, y),
+                        (x, y.wrapping_sub(1)),
+                        (x, y + 1),
+                    ];
+
+                    for (x, y) in neighbors {
+                        if x < self.width && y < self.height && occupied[y][x] {
+                            occupied[y][x] = false;
+                            stack.push((x, y));
+                        }
+                    }
+                }
+
+                largest = largest.max(size);
+            }
+        }
+
+        largest
+    }
+
+    /// Finds the first second (counting from now) at which the robots form
+    /// a picture.
+    ///
+    /// x coordinates repeat every `width` seconds and y coordinates every
+    /// `height`, so positions repeat every `lcm(width, height)` seconds and
+    /// only that many frames need checking. With `Score::Variance` it's even
+    /// fewer: the x and y variances can be computed for each second of their
+    /// own period, and the second with the least sum of the two found by
+    /// pairing them up with the Chinese remainder theorem. Unless the sizes
+    /// are coprime, only some pairs come up, so the least x and y variances
+    /// may not be at the same second.
+    fn find_picture(&self, score: Score) -> Option<i64> {
+        let (w, h) = (self.width as i64, self.height as i64);
+
+        let lcm = w / ext_gcd(w as i128, h as i128).0 as i64 * h;
+
+        match score {
+            Score::Variance => {
+                let variances = |period: i64, var: fn((f64, f64)) -> f64| {
+                    (0..period)
+                        .map(|t| {
+                            let mut robots = self.clone();
+                            robots.update_n(t);
+                            var(robots.variances())
+                        })
+                        .collect::<Vec<_>>()
+                };
+
+                let vx = variances(w, |v| v.0);
+                let vy = variances(h, |v| v.1);
+
+                (0..w)
+                    .flat_map(|tx| (0..h).map(move |ty| (tx, ty)))
+                    .filter_map(|(tx, ty)| {
+                        Some((crt(tx, w, ty, h)?, vx[tx as usize] + vy[ty as usize]))
+                    })
+                    .min_by(|a, b| a.1.total_cmp(&b.1).then(a.0.cmp(&b.0)))
+                    .map(|(t, _)| t)
+            },
+            Score::LargestCluster => {
+                let mut robots = self.clone();
+                let mut best = (0, 0);
+
+                for t in 0..lcm {
+                    let size = robots.largest_cluster();
+
+                    if size > best.1 {
+                        best = (t, size);
+                    }
+
+                    robots.update_n(1);
+                }
+
+                Some(best.0)
+            },
+        }
     }
 }
 
-impl<const W: usize, const H: usize> std::fmt::Display for Robots<W, H> {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        for y in 0..H as i64 {
-            // Slow, but this should be ok :p
-            let line = (0..W as i64)
-                .map(|x| if self.0.iter().any(|robot| robot.p == (x, y)) {
-                    '*'
-                } else {
-                    '.'
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ImageFormat {
+    /// Plain PBM: black where there's at least one robot.
+    Pbm,
+    /// Plain PPM: grey levels by the number of robots on a tile, white being
+    /// the most crowded tile of the frame.
+    Ppm,
+}
+
+impl ImageFormat {
+    fn extension(&self) -> &'static str {
+        match self {
+            ImageFormat::Pbm => "pbm",
+            ImageFormat::Ppm => "ppm",
+        }
+    }
+}
+
+impl TryFrom<&str> for ImageFormat {
+    type Error = &'static str;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        match value {
+            "pbm" => Ok(ImageFormat::Pbm),
+            "ppm" => Ok(ImageFormat::Ppm),
+            _ => Err("unknown image format"),
+        }
+    }
+}
+
+impl Robots {
+    /// Number of robots on each tile, row by row.
+    fn raster(&self) -> Vec<u32> {
+        let mut counts = vec![0; self.width * self.height];
+
+        for robot in &self.robots {
+            counts[robot.p.1 as usize * self.width + robot.p.0 as usize] += 1;
+        }
+
+        counts
+    }
+
+    fn to_image(&self, format: ImageFormat) -> String {
+        let counts = self.raster();
+
+        let mut image = match format {
+            ImageFormat::Pbm => format!("P1\n{} {}\n", self.width, self.height),
+            ImageFormat::Ppm => format!("P3\n{} {}\n255\n", self.width, self.height),
+        };
+
+        let max = counts.iter().copied().max().unwrap_or(0).max(1);
+
+        for row in counts.chunks(self.width) {
+            let pixels = row.iter()
+                .map(|&n| match format {
+                    ImageFormat::Pbm => if n > 0 { "1" } else { "0" }.to_string(),
+                    ImageFormat::Ppm => {
+                        let level = 255 * n / max;
+                        format!("{} {} {}", level, level, level)
+                    },
                 })
+                .collect::<Vec<_>>();
+
+            image.push_str(&pixels.join(" "));
+            image.push('\n');
+        }
+
+        image
+    }
+
+    /// Writes one image per `stride` seconds in `seconds` to `dir`, named
+    /// after the second it shows (e.g., `frame-07093.pbm`).
+    fn export_frames(
+        &self,
+        seconds: Range<i64>,
+        stride: usize,
+        dir: &std::path::Path,
+        format: ImageFormat,
+    ) -> std::io::Result<usize> {
+        std::fs::create_dir_all(dir)?;
+
+        let mut robots = self.clone();
+        let mut now = 0;
+        let mut written = 0;
+
+        for t in seconds.step_by(stride.max(1)) {
+            robots.update_n(t - now);
+            now = t;
+
+            let path = dir.join(format!("frame-{:05}.{}", t, format.extension()));
+
+            std::fs::write(path, robots.to_image(format))?;
+            written += 1;
+        }
+
+        Ok(written)
+    }
+
+    /// Steps through the frames on the terminal, starting at second `start`.
+    /// Each line read from `input` is a command: empty or `n` moves forward
+    /// `stride` seconds, `p` moves back, a number jumps to that second, and
+    /// `q` quits.
+    fn animate(&self, start: i64, stride: i64, input: impl BufRead) {
+        let mut robots = self.clone();
+        let mut now = start;
+
+        robots.update_n(now);
+
+        let mut lines = input.lines();
+
+        loop {
+            // Clear the screen and move the cursor to the top left.
+            print!("\x1b[2J\x1b[H{}", robots);
+            println!("t = {} ([n]ext, [p]rev, <second>, [q]uit; stride {})", now, stride);
+
+            let Some(Ok(line)) = lines.next() else {
+                break;
+            };
+
+            let delta = match line.trim() {
+                "" | "n" => stride,
+                "p" => -stride,
+                "q" => break,
+                t => match t.parse::<i64>() {
+                    Ok(t) => t - now,
+                    Err(_) => continue,
+                },
+            };
+
+            robots.update_n(delta);
+            now += delta;
+        }
+    }
+}
+
+impl std::fmt::Display for Robots {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for row in self.raster().chunks(self.width) {
+            let line = row.iter()
+                .map(|&n| if n > 0 { '*' } else { '.' })
                 .collect::<String>();
 
             writeln!(f, "{}", line)?;
@@ -100,58 +433,111 @@ impl<const W: usize, const H: usize> std::fmt::Display for Robots<W, H> {
     }
 }
 
+/// Parses a pair such as `101x103`.
+fn parse_dims(value: &str) -> Result<(usize, usize), &'static str> {
+    let (a, b) = value.split_once('x').ok_or("expected dimensions like 101x103")?;
+
+    let dim = |n: &str| match n.parse::<usize>() {
+        Ok(0) => Err("dimensions must not be zero"),
+        Ok(n) => Ok(n),
+        Err(_) => Err("invalid dimension"),
+    };
+
+    Ok((dim(a)?, dim(b)?))
+}
+
 fn main() {
-    let mut num_robots_in_quadrant: HashMap<u8, usize> = HashMap::new();
+    // e.g., `cargo run --bin day14 -- --input src/bin/day14-sample.txt --size 11x7`,
+    // `-- --partition 3x3`, `-- --cluster`,
+    // `-- --frames 7000..7200 --stride 10 --dir frames --format ppm`, or
+    // `-- --animate 7093 --stride 101`
+    let options = [
+        "--input", "--size", "--partition", "--stride", "--frames", "--dir", "--format", "--animate",
+    ];
 
-    INPUT.lines()
-        .for_each(|line| {
-            let mut robot: Robot<101, 103> = line.try_into().unwrap();
+    let args = match Args::parse(std::env::args().skip(1), &options) {
+        Ok(args) => args,
+        Err(e) => {
+            println!("error: {}", e);
+            return;
+        },
+    };
 
-            robot.update_n(100);
+    let input = args.value("--input").map(|path| std::fs::read_to_string(path).unwrap());
 
-            if let Some(quadrant) = robot.quadrant() {
-                num_robots_in_quadrant.entry(quadrant)
-                    .and_modify(|n| *n += 1)
-                    .or_insert(1usize);
-            }
-        });
-
-    println!("{:?}", num_robots_in_quadrant.values().product::<usize>());
-
-    // part 2: :)
-    // ............................*.*.....*******************************..................................
-    // .*..................................*.............................*..................................
-    // ....................................*.............................*..................................
-    // ...*................................*.............................*..........................*.......
-    // ..........................*.........*.............................*...................*..............
-    // ....................................*..............*..............*..................................
-    // ...............................*....*.............***.............*..................................
-    // ....................................*............*****............*..................................
-    // ....................................*...........*******...........*......................*........*..
-    // ....................................*..........*********..........*............*................*....
-    // ....................................*............*****............*........*.........................
-    // ....................................*...........*******...........*..................................
-    // .....................*............*.*..........*********..........*.*............................*...
-    // ....................................*.........***********.........*..................................
-    // ....................................*........*************........*..................................
-    // ....................................*..........*********..........*.*..................**............
-    // ....................................*.........***********.........*..................................
-    // ....................................*........*************........*..................................
-    // ......................*.............*.......***************.......*..................................
-    // .......*............................*......*****************......*..................................
-    // ...........*..*.....................*........*************........*................*.................
-    // ....................................*.......***************.......*...........................*......
-    // ....................................*......*****************......*.....*.................*..........
-    // ......*.............................*.....*******************.....*..........*.......................
-    // ................*...................*....*********************....*...................*..............
-    // ..................................*.*.............***.............*..................................
-    // ....................................*.............***.............*......*...........................
-    // ....................................*.............***.............*..................................
-    // ....................................*.............................*..................................
-    // ............................*.......*.............................*..................................
-    // ....................................*.............................*..................................
-    // ...............*....................*.............................*..............*...................
-    // ..............*.........*...........*******************************.....................*..........
+    let (width, height) = match args.value("--size").map_or(Ok((101, 103)), parse_dims) {
+        Ok(size) => size,
+        Err(e) => {
+            println!("error: {}", e);
+            return;
+        },
+    };
+
+    let robots = Robots::new(input.as_deref().unwrap_or(INPUT), width, height).unwrap();
+
+    let mut after = robots.clone();
+
+    after.update_n(100);
+
+    println!("{}", after.safety_factor(2, 2));
+
+    if let Some(partition) = args.value("--partition") {
+        let (cols, rows) = match parse_dims(partition) {
+            Ok(dims) => dims,
+            Err(e) => {
+                println!("error: {}", e);
+                return;
+            },
+        };
+
+        for row in after.partition(cols, rows) {
+            println!("{}", row.iter().map(|n| format!("{:>5}", n)).collect::<String>());
+        }
+
+        println!("safety factor: {}", after.safety_factor(cols, rows));
+    }
+
+    // part 2: find the Easter egg
+    let score = if args.has("--cluster") {
+        Score::LargestCluster
+    } else {
+        Score::Variance
+    };
+
+    match robots.find_picture(score) {
+        Some(t) => {
+            println!("{}", t);
+
+            let mut picture = robots.clone();
+
+            picture.update_n(t);
+
+            print!("{}", picture);
+        },
+        None => println!("no picture found"),
+    }
+
+    let stride = args.value("--stride").map_or(1, |stride| stride.parse::<usize>().unwrap());
+
+    if let Some(seconds) = args.value("--frames") {
+        let (from, to) = seconds.split_once("..").expect("expected a range like 0..100");
+        let seconds = from.parse::<i64>().unwrap()..to.parse::<i64>().unwrap();
+
+        let dir = std::path::Path::new(args.value("--dir").unwrap_or("frames"));
+        let format = args.value("--format")
+            .map_or(Ok(ImageFormat::Pbm), ImageFormat::try_from)
+            .unwrap();
+
+        let written = robots.export_frames(seconds, stride, dir, format).unwrap();
+
+        println!("wrote {} frames to {}", written, dir.display());
+    }
+
+    if let Some(start) = args.value("--animate") {
+        let start = start.parse::<i64>().unwrap();
+
+        robots.animate(start, stride as i64, std::io::stdin().lock());
+    }
 }
 
 #[cfg(test)]
@@ -160,14 +546,126 @@ mod test {
 
     #[test]
     fn test_update_n() {
-        let mut robot: Robot<11, 7> = Robot { p: (2, 4), v: (2, -3) };
+        let mut robot = Robot { p: (2, 4), v: (2, -3) };
 
-        let mut robot2 = robot.clone();
+        let mut robot2 = robot;
 
         for _ in 0..7 {
-            robot.update();
+            robot.update((11, 7));
         }
 
-        robot2.update_n(7);
+        robot2.update_n(7, (11, 7));
+
+        assert_eq!(robot, robot2);
+    }
+
+    #[test]
+    fn test_safety_factor() {
+        let mut robots = Robots::new(include_str!("./day14-sample.txt"), 11, 7).unwrap();
+
+        robots.update_n(100);
+
+        assert_eq!(robots.partition(2, 2), vec![vec![1, 3], vec![4, 1]]);
+        assert_eq!(robots.safety_factor(2, 2), 12);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_band() {
+        // The middle tile of an odd length is on the dividing line ...
+        assert_eq!((0..5).map(|x| band(x, 5, 2)).collect::<Vec<_>>(),
+            vec![Some(0), Some(0), None, Some(1), Some(1)]);
+
+        // ... but an even length splits between two tiles.
+        assert_eq!((0..4).map(|x| band(x, 4, 2)).collect::<Vec<_>>(),
+            vec![Some(0), Some(0), Some(1), Some(1)]);
+
+        assert_eq!((0..7).map(|x| band(x, 7, 3)).collect::<Vec<_>>(),
+            vec![Some(0), Some(0), None, Some(1), None, Some(2), Some(2)]);
+    }
+
+    #[test]
+    fn test_parse_dims() {
+        assert_eq!(parse_dims("101x103"), Ok((101, 103)));
+        assert!(parse_dims("0x2").is_err());
+        assert!(parse_dims("11x0").is_err());
+        assert!(parse_dims("11").is_err());
+    }
+
+    #[test]
+    fn test_crt() {
+        assert_eq!(crt(2, 3, 3, 5), Some(8));
+        assert_eq!(crt(0, 101, 0, 103), Some(0));
+        assert_eq!(crt(1, 4, 2, 6), None);
+        assert_eq!(crt(1, 4, 3, 6), Some(9));
+    }
+
+    #[test]
+    fn test_find_picture() {
+        // A 3x3 block that's been scattered for 5 seconds.
+        let mut robots = Robots {
+            width: 11,
+            height: 7,
+            robots: (0..9)
+                .map(|i| Robot { p: (4 + i % 3, 2 + i / 3), v: (i % 4 - 2, i % 3 + 1) })
+                .collect(),
+        };
+
+        robots.update_n(-5);
+
+        assert_eq!(robots.find_picture(Score::Variance), Some(5));
+        assert_eq!(robots.find_picture(Score::LargestCluster), Some(5));
+    }
+
+    #[test]
+    fn test_find_picture_shared_factor() {
+        // x and y coordinates repeat every 12 and 8 seconds, so positions
+        // repeat every 24 seconds, and the seconds with the least x and y
+        // variance don't line up.
+        let robots = Robots::new(include_str!("./day14-sample.txt"), 12, 8).unwrap();
+
+        let score = |t: i64| {
+            let mut robots = robots.clone();
+            robots.update_n(t);
+            let (vx, vy) = robots.variances();
+            vx + vy
+        };
+
+        let argmin = |period: i64, var: fn((f64, f64)) -> f64| {
+            (0..period)
+                .min_by(|&a, &b| {
+                    let (mut ra, mut rb) = (robots.clone(), robots.clone());
+                    ra.update_n(a);
+                    rb.update_n(b);
+                    var(ra.variances()).total_cmp(&var(rb.variances()))
+                })
+                .unwrap()
+        };
+
+        assert_eq!(crt(argmin(12, |v| v.0), 12, argmin(8, |v| v.1), 8), None);
+
+        let best = (0..24).min_by(|&a, &b| score(a).total_cmp(&score(b))).unwrap();
+
+        assert_eq!(robots.find_picture(Score::Variance), Some(best));
+        assert!(robots.find_picture(Score::LargestCluster).is_some_and(|t| t < 24));
+    }
+
+    #[test]
+    fn test_images() {
+        let robots = Robots {
+            width: 3,
+            height: 2,
+            robots: vec![
+                Robot { p: (0, 0), v: (1, 0) },
+                Robot { p: (0, 0), v: (0, 1) },
+                Robot { p: (2, 1), v: (0, 0) },
+            ],
+        };
+
+        assert_eq!(robots.to_string(), "*..\n..*\n");
+        assert_eq!(robots.to_image(ImageFormat::Pbm), "P1\n3 2\n1 0 0\n0 0 1\n");
+        assert_eq!(
+            robots.to_image(ImageFormat::Ppm),
+            "P3\n3 2\n255\n255 255 255 0 0 0 0 0 0\n0 0 0 0 0 0 127 127 127\n",
+        );
+    }
+}
@@ -1,56 +1,23 @@
+use std::io::BufRead;
+
+use aoc2024::Args;
+
 const INPUT: &str = include_str!("./day15.txt");
 
-#[derive(PartialEq, Eq, Clone, Copy)]
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
 enum Tile {
     Empty,
-    Box,
-    BoxL,
-    BoxR,
+    /// Part of the box with the given id; see `Warehouse::boxes`.
+    Box(usize),
     Wall,
 }
 
-impl TryInto<Tile> for char {
-    type Error = &'static str;
-
-    fn try_into(self) -> Result<Tile, Self::Error> {
-        if self == '.' {
-            Ok(Tile::Empty)
-        } else if self == 'O' {
-            Ok(Tile::Box)
-        } else if self == '[' {
-            Ok(Tile::BoxL)
-        } else if self == ']' {
-            Ok(Tile::BoxR)
-        } else if self == '#' {
-            Ok(Tile::Wall)
-        } else {
-            Err("meheh")
-        }
-    }
-}
-
-impl TryFrom<Tile> for char {
-    type Error = &'static str;
-
-    fn try_from(tile: Tile) -> Result<Self, Self::Error> {
-        match tile {
-            Tile::Box => Ok('O'),
-            Tile::BoxL => Ok('['),
-            Tile::BoxR => Ok(']'),
-            Tile::Empty => Ok('.'),
-            Tile::Wall => Ok('#'),
-        }
-    }
-}
-
 impl std::fmt::Display for Tile {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}", {
-            if let Ok(c) = self.clone().try_into() {
-                c
-            } else {
-                '.'
-            }
+        write!(f, "{}", match self {
+            Tile::Empty => '.',
+            Tile::Box(_) => 'O',
+            Tile::Wall => '#',
         })
     }
 }
@@ -81,6 +48,17 @@ impl TryFrom<char> for Command {
     }
 }
 
+impl From<Command> for char {
+    fn from(command: Command) -> Self {
+        match command {
+            Command::Up => '^',
+            Command::Down => 'v',
+            Command::Left => '<',
+            Command::Right => '>',
+        }
+    }
+}
+
 impl Command {
     fn delta(&self) -> (i64, i64) {
         match self {
@@ -90,215 +68,430 @@ impl Command {
             Command::Right => (0, 1),
         }
     }
+
+    fn apply(&self, (y, x): (usize, usize)) -> (usize, usize) {
+        self.reverse_if(false, (y, x))
+    }
+
+    /// Moves `(y, x)` one tile in this direction, or the opposite one if
+    /// `reverse` is set.
+    fn reverse_if(&self, reverse: bool, (y, x): (usize, usize)) -> (usize, usize) {
+        let (dy, dx) = self.delta();
+        let sign = if reverse { -1 } else { 1 };
+
+        (
+            (y as i64 + sign * dy) as usize,
+            (x as i64 + sign * dx) as usize,
+        )
+    }
+}
+
+/// A tile that changed in a step: its position, and the tile before and after.
+type Change = ((usize, usize), Tile, Tile);
+
+/// Everything a single step did, so that it can be undone and redone.
+#[derive(Clone, Debug)]
+struct Delta {
+    command: Command,
+    /// The robot's position before and after the step.
+    robot: ((usize, usize), (usize, usize)),
+    /// Tile changes in the order they were made.
+    changes: Vec<Change>,
+    /// Ids of the boxes pushed, with their top-left corners before the push.
+    pushed: Vec<(usize, (usize, usize))>,
+}
+
+impl Delta {
+    fn moved(&self) -> bool {
+        self.robot.0 != self.robot.1
+    }
+}
+
+impl std::fmt::Display for Delta {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} {:?} -> {:?}", char::from(self.command), self.robot.0, self.robot.1)?;
+
+        if !self.moved() {
+            return write!(f, " blocked");
+        }
+
+        if !self.pushed.is_empty() {
+            write!(f, " pushed")?;
+        }
+
+        for (_, (y, x)) in &self.pushed {
+            write!(f, " ({}, {})", y, x)?;
+        }
+
+        Ok(())
+    }
 }
 
 struct Warehouse {
     robot: (usize, usize),
     tiles: Vec<Vec<Tile>>,
+    /// The tiles each box covers, indexed by id. A box can have any shape,
+    /// e.g., `O` is a 1x1 box, `[]` a 2x1 box, `[==]` a 4x1 box, and a box
+    /// spanning several rows is drawn as a letter (see `labels`).
+    boxes: Vec<Vec<(usize, usize)>>,
+    /// Steps taken so far, oldest first.
+    history: Vec<Delta>,
+    /// Steps undone, most recently undone last.
+    future: Vec<Delta>,
+    /// Changes made by the step in progress.
+    changes: Vec<Change>,
 }
 
 impl Warehouse {
-    fn can_move_2x1_box(
-        &self,
-        (y, x): (usize, usize),
-        command: &Command,
-    ) -> bool {
-        let (dy, dx) = command.delta();
-
-        match (self.tiles[y][x], command) {
-            (Tile::BoxL, Command::Left) | (Tile::BoxL, Command::Right) |
-            (Tile::BoxR, Command::Left) | (Tile::BoxR, Command::Right) => {
-                // Can move 2x1 box horizontally?
-                let (y_next, x_next) = (
-                    (y as i64 + dy) as usize,
-                    (x as i64 + dx) as usize,
-                );
-
-                if self.tiles[y_next][x_next] == Tile::Wall {
-                    return false;
-                } else if self.can_move_2x1_box((y_next, x_next), command) {
-                        return true;
-                }
+    fn set(&mut self, (y, x): (usize, usize), tile: Tile) {
+        self.changes.push(((y, x), self.tiles[y][x], tile));
+        self.tiles[y][x] = tile;
+    }
 
-                false
-            },
-            (Tile::BoxL, Command::Up) | (Tile::BoxL, Command::Down) => {
-                // Can move 2x1 box vertically?
-                let (y_next, x_next) = (
-                    (y as i64 + dy) as usize,
-                    (x as i64 + dx) as usize,
-                );
-
-                if self.tiles[y_next][x_next] == Tile::Wall ||
-                    self.tiles[y_next][x_next + 1] == Tile::Wall {
-                    return false;
-                } else if self.can_move_2x1_box((y_next, x_next), command) && 
-                    self.can_move_2x1_box((y_next, x_next + 1), command) {
-                        return true;
-                }
+    /// Places a box covering `cells`, which must all be empty; returns its id.
+    /// The box must be in one piece, and unbroken within each row.
+    fn add_box(&mut self, cells: Vec<(usize, usize)>) -> Result<usize, &'static str> {
+        if cells.is_empty() {
+            return Err("box must cover at least one tile");
+        }
 
-                false
-            },
-            (Tile::BoxR, Command::Up) | (Tile::BoxR, Command::Down) => {
-                // Can move 2x1 box vertically?
-                let (y_next, x_next) = (
-                    (y as i64 + dy) as usize,
-                    (x as i64 + dx) as usize,
-                );
-
-                if self.tiles[y_next][x_next] == Tile::Wall ||
-                    self.tiles[y_next][x_next - 1] == Tile::Wall {
-                    return false;
-                } else if self.can_move_2x1_box((y_next, x_next), command) && 
-                    self.can_move_2x1_box((y_next, x_next - 1), command) {
-                        return true;
+        let mut sorted = cells.clone();
+
+        sorted.sort();
+        sorted.dedup();
+
+        if sorted.len() != cells.len() {
+            return Err("box covers a tile more than once");
+        }
+
+        if sorted.windows(2).any(|pair| pair[0].0 == pair[1].0 && pair[1].1 != pair[0].1 + 1) {
+            return Err("box is split within a row");
+        }
+
+        // Every tile must be reachable from the first through the others.
+        let mut reached = vec![sorted[0]];
+        let mut frontier = vec![sorted[0]];
+
+        while let Some((y, x)) = frontier.pop() {
+            for next in [(y.wrapping_sub(1), x), (y + 1, x), (y, x.wrapping_sub(1)), (y, x + 1)] {
+                if sorted.binary_search(&next).is_ok() && !reached.contains(&next) {
+                    reached.push(next);
+                    frontier.push(next);
                 }
+            }
+        }
 
-                false
-            },
-            (Tile::Empty, _) => {
-                return true;
-            },
-            _ => panic!(),
+        if reached.len() != cells.len() {
+            return Err("box isn't in one piece");
+        }
+
+        for &(y, x) in &cells {
+            match self.tiles.get(y).and_then(|row| row.get(x)) {
+                Some(Tile::Empty) if self.robot != (y, x) => {},
+                _ => return Err("box must be placed on empty tiles"),
+            }
         }
-    }
 
-    fn move_2x1_box(
-        &mut self,
-        (y, x): (usize, usize),
-        command: &Command,
-    ) {
-        let (dy, dx) = command.delta();
+        let id = self.boxes.len();
 
-        match (self.tiles[y][x], command) {
-            (Tile::BoxL, Command::Left) | (Tile::BoxL, Command::Right) |
-            (Tile::BoxR, Command::Left) | (Tile::BoxR, Command::Right) => {
-                let (y_next, x_next) = (
-                    (y as i64 + dy) as usize,
-                    (x as i64 + dx) as usize,
-                );
+        for &(y, x) in &cells {
+            self.tiles[y][x] = Tile::Box(id);
+        }
 
-                self.move_2x1_box((y_next, x_next), command);
+        self.boxes.push(cells);
 
-                self.tiles[y_next][x_next] = self.tiles[y][x];
-                self.tiles[y][x] = Tile::Empty;
-            },
-            (Tile::BoxL, Command::Up) | (Tile::BoxL, Command::Down) => {
-                let (y_next, x_next) = (
-                    (y as i64 + dy) as usize,
-                    (x as i64 + dx) as usize,
-                );
-
-                self.move_2x1_box((y_next, x_next), command);
-                self.move_2x1_box((y_next, x_next + 1), command);
-                self.tiles[y_next][x_next] = self.tiles[y][x];
-                self.tiles[y_next][x_next + 1] = self.tiles[y][x + 1];
-                self.tiles[y][x] = Tile::Empty;
-                self.tiles[y][x + 1] = Tile::Empty;
-            },
-            (Tile::BoxR, Command::Up) | (Tile::BoxR, Command::Down) => {
-                let (y_next, x_next) = (
-                    (y as i64 + dy) as usize,
-                    (x as i64 + dx) as usize,
-                );
-
-                self.move_2x1_box((y_next, x_next), command);
-                self.move_2x1_box((y_next, x_next - 1), command);
-                self.tiles[y_next][x_next] = self.tiles[y][x];
-                self.tiles[y_next][x_next - 1] = self.tiles[y][x - 1];
-                self.tiles[y][x] = Tile::Empty;
-                self.tiles[y][x - 1] = Tile::Empty;
-            },
-            _ => {},
+        Ok(id)
+    }
+
+    /// Top-left corner of the box's bounding rectangle.
+    fn corner(&self, id: usize) -> (usize, usize) {
+        let cells = &self.boxes[id];
+
+        (
+            cells.iter().map(|&(y, _)| y).min().unwrap(),
+            cells.iter().map(|&(_, x)| x).min().unwrap(),
+        )
+    }
+
+    /// Finds every box that moves if something at `from` moves one tile in
+    /// the direction of `command`: the boxes in the way, the boxes in their
+    /// way, and so on. Returns `None` if any of them would hit a wall.
+    fn push_closure(&self, from: (usize, usize), command: Command) -> Option<Vec<usize>> {
+        let mut pushed = Vec::new();
+        let mut seen = vec![false; self.boxes.len()];
+        let mut frontier = vec![command.apply(from)];
+
+        while let Some((y, x)) = frontier.pop() {
+            match self.tiles[y][x] {
+                Tile::Empty => {},
+                Tile::Wall => return None,
+                Tile::Box(id) => {
+                    if !seen[id] {
+                        seen[id] = true;
+                        pushed.push(id);
+                        frontier.extend(self.boxes[id].iter().map(|&cell| command.apply(cell)));
+                    }
+                },
+            }
         }
+
+        Some(pushed)
     }
 
+    /// Moves the robot, pushing boxes if it can, and records the step so
+    /// that it can be undone. Anything undone can no longer be redone.
     fn step(&mut self, command: Command) {
-        let (dy, dx) = command.delta();
+        let robot = self.robot;
 
-        let (y_next, x_next) = (
-            (self.robot.0 as i64 + dy) as usize,
-            (self.robot.1 as i64 + dx) as usize
-        );
+        let mut pushed = Vec::new();
 
-        match (self.tiles[y_next][x_next], command) {
-            (Tile::Empty, _) => {
-                self.robot = (y_next, x_next);
-            },
-            (Tile::Box, _) => {
-                // As the robot (@) attempts to move, if there are any boxes (O)
-                // in the way, the robot will also attempt to push those boxes.
-                //
-                // E.g.,
-                //      543210           43210
-                //     (#.OOO@, [<]) -> (#OOO@., [])
-                //
-                // Approach:
-                // - Find the first empty tile along the way
-                //   - If a wall tile is encountered before an empty tile, it
-                //     means the robot cannot push any boxes
-                let mut i = 1;
-
-                let first_empty_tile = loop {
-                    let (y, x) = (
-                        (self.robot.0 as i64 + i * dy) as usize,
-                        (self.robot.1 as i64 + i * dx) as usize
-                    );
-
-                    match self.tiles[y][x] {
-                        Tile::Wall => break None,
-                        Tile::Empty => break Some((y, x)),
-                        _ => { i += 1 },
-                    }
-                };
+        if let Some(ids) = self.push_closure(robot, command) {
+            pushed = ids.iter().map(|&id| (id, self.corner(id))).collect::<Vec<_>>();
 
-                if let Some((y, x)) = first_empty_tile {
-                    self.tiles[y_next][x_next] = Tile::Empty;
-                    self.robot = (y_next, x_next);
-                    self.tiles[y][x] = Tile::Box;
+            // Lift every box before putting any of them down, since boxes can
+            // move into each other's old tiles.
+            for &id in &ids {
+                for cell in self.boxes[id].clone() {
+                    self.set(cell, Tile::Empty);
                 }
-            },
-            (Tile::BoxL, _) | (Tile::BoxR, _) => {
-                if self.can_move_2x1_box((y_next, x_next), &command) {
-                    self.move_2x1_box((y_next, x_next), &command);
-                    self.robot = (y_next, x_next);
+            }
+
+            for &id in &ids {
+                let cells = self.boxes[id].iter()
+                    .map(|&cell| command.apply(cell))
+                    .collect::<Vec<_>>();
+
+                for &cell in &cells {
+                    self.set(cell, Tile::Box(id));
                 }
-            },
-            _ => {},
+
+                self.boxes[id] = cells;
+            }
+
+            self.robot = command.apply(robot);
         }
+
+        pushed.sort_by_key(|&(_, corner)| corner);
+
+        self.history.push(Delta {
+            command,
+            robot: (robot, self.robot),
+            changes: std::mem::take(&mut self.changes),
+            pushed,
+        });
+
+        self.future.clear();
+
+        debug_assert_eq!(self.check(), Ok(()), "after step {}", self.history.len());
     }
 
-    fn sum_gps_coordinates(&self) -> usize {
-        let mut res = 0usize;
+    /// Checks that the robot stands on an empty tile, that the tiles and
+    /// `boxes` agree on where every box is (so no box has appeared or gone
+    /// missing), and that no box has been torn apart: within each row, a
+    /// box's tiles are next to each other, i.e., every `[` still has its `]`.
+    fn check(&self) -> Result<(), &'static str> {
+        if self.tiles.get(self.robot.0).and_then(|row| row.get(self.robot.1)) != Some(&Tile::Empty) {
+            return Err("robot isn't on an empty tile");
+        }
 
-        for i in 0..self.tiles.len() {
-            for j in 0..self.tiles[0].len() {
-                if matches!(self.tiles[i][j], Tile::Box | Tile::BoxL) {
-                    res += 100 * i + j;
+        let num_box_tiles = self.tiles.iter()
+            .flatten()
+            .filter(|tile| matches!(tile, Tile::Box(_)))
+            .count();
+
+        if num_box_tiles != self.boxes.iter().map(Vec::len).sum::<usize>() {
+            return Err("number of box tiles has changed");
+        }
+
+        for (id, cells) in self.boxes.iter().enumerate() {
+            if cells.iter().any(|&(y, x)| self.tiles[y][x] != Tile::Box(id)) {
+                return Err("box isn't where it's recorded to be");
+            }
+
+            let mut cells = cells.clone();
+
+            cells.sort();
+
+            for pair in cells.windows(2) {
+                let ((y0, x0), (y1, x1)) = (pair[0], pair[1]);
+
+                if y0 == y1 && x1 != x0 + 1 {
+                    return Err("box is split within a row");
                 }
             }
         }
 
-        res
+        Ok(())
+    }
+
+    /// Reverts the last step; returns `false` if there's none.
+    fn undo(&mut self) -> bool {
+        let Some(delta) = self.history.pop() else {
+            return false;
+        };
+
+        for &((y, x), before, _) in delta.changes.iter().rev() {
+            self.tiles[y][x] = before;
+        }
+
+        self.shift(&delta, true);
+        self.robot = delta.robot.0;
+        self.future.push(delta);
+
+        debug_assert_eq!(self.check(), Ok(()), "after undoing step {}", self.history.len() + 1);
+
+        true
+    }
+
+    /// Takes the last undone step again; returns `false` if there's none.
+    fn redo(&mut self) -> bool {
+        let Some(delta) = self.future.pop() else {
+            return false;
+        };
+
+        for &((y, x), _, after) in &delta.changes {
+            self.tiles[y][x] = after;
+        }
+
+        self.shift(&delta, false);
+        self.robot = delta.robot.1;
+        self.history.push(delta);
+
+        debug_assert_eq!(self.check(), Ok(()), "after redoing step {}", self.history.len());
+
+        true
+    }
+
+    /// Moves the cells of the boxes pushed in `delta` along with its tiles.
+    fn shift(&mut self, delta: &Delta, reverse: bool) {
+        for &(id, _) in &delta.pushed {
+            for cell in self.boxes[id].iter_mut() {
+                *cell = delta.command.reverse_if(reverse, *cell);
+            }
+        }
+    }
+
+    /// Undoes or redoes steps until exactly `n` steps have been taken.
+    fn replay_to(&mut self, n: usize) -> Result<(), &'static str> {
+        if n > self.history.len() + self.future.len() {
+            return Err("not that many steps recorded");
+        }
+
+        while self.history.len() > n {
+            self.undo();
+        }
+
+        while self.history.len() < n {
+            self.redo();
+        }
+
+        debug_assert_eq!(self.check(), Ok(()), "after replaying to step {}", n);
+
+        Ok(())
+    }
+
+    /// One line per step taken: the command, the robot's position before and
+    /// after, and either `blocked` or the top-left tiles of the boxes pushed.
+    fn trace(&self) -> String {
+        self.history.iter()
+            .enumerate()
+            .map(|(i, delta)| format!("{} {}\n", i + 1, delta))
+            .collect()
+    }
+
+    /// The commands of the steps taken, in the format of the puzzle input's
+    /// second section, i.e., `width` commands per line.
+    fn command_log(&self, width: usize) -> String {
+        let commands = self.history.iter()
+            .map(|delta| char::from(delta.command))
+            .collect::<Vec<_>>();
+
+        commands.chunks(width.max(1))
+            .map(|line| line.iter().collect::<String>() + "\n")
+            .collect()
     }
 
+    /// A box's GPS coordinate is measured to the top-left corner of its
+    /// bounding rectangle.
+    fn sum_gps_coordinates(&self) -> usize {
+        (0..self.boxes.len())
+            .map(|id| self.corner(id))
+            .map(|(y, x)| 100 * y + x)
+            .sum()
+    }
+
+    /// Doubles the warehouse's width, and every box's with it. This starts a
+    /// new history.
     fn expand(&mut self) {
         self.robot = (self.robot.0, self.robot.1 * 2);
+        self.history.clear();
+        self.future.clear();
 
         self.tiles = self.tiles.iter()
             .map(|row| {
                 row.iter()
-                    .flat_map(|tile| {
-                        match tile {
-                            Tile::Empty => vec![Tile::Empty, Tile::Empty],
-                            Tile::Box => vec![Tile::BoxL, Tile::BoxR],
-                            Tile::Wall => vec![Tile::Wall, Tile::Wall],
-                            _ => panic!("ugh"),
-                        }
-                    })
+                    .flat_map(|&tile| [tile, tile])
                     .collect::<Vec<_>>()
             })
             .collect::<Vec<_>>();
+
+        for cells in self.boxes.iter_mut() {
+            *cells = cells.iter()
+                .flat_map(|&(y, x)| [(y, 2 * x), (y, 2 * x + 1)])
+                .collect();
+        }
+    }
+
+    /// Letters for the boxes that span several rows, which `[`, `=` and `]`
+    /// can't draw. Each such box gets the first letter that no such box
+    /// touching it has, so that the parser can tell them apart; `O` is
+    /// skipped, and `?` (which doesn't parse) is only left if a box touches
+    /// more than 50 others.
+    fn labels(&self) -> Vec<Option<char>> {
+        let letters = ('a'..='z').chain('A'..='Z').filter(|&c| c != 'O');
+
+        let mut labels = vec![None; self.boxes.len()];
+
+        for (id, cells) in self.boxes.iter().enumerate() {
+            if cells.iter().all(|&(y, _)| y == cells[0].0) {
+                continue;
+            }
+
+            // Boxes are never next to the edge, which is all walls.
+            let taken = cells.iter()
+                .flat_map(|&(y, x)| [(y - 1, x), (y + 1, x), (y, x - 1), (y, x + 1)])
+                .filter_map(|(y, x)| match self.tiles[y][x] {
+                    Tile::Box(other) => labels[other],
+                    _ => None,
+                })
+                .collect::<Vec<_>>();
+
+            labels[id] = Some(letters.clone().find(|c| !taken.contains(c)).unwrap_or('?'));
+        }
+
+        labels
+    }
+
+    /// How a box tile is drawn: its letter from `labels`, if it has one; `O`
+    /// for a 1x1 box; otherwise by whether the box continues to the left and
+    /// to the right, i.e., `[`, `=` or `]`.
+    fn box_glyph(&self, labels: &[Option<char>], id: usize, (y, x): (usize, usize)) -> char {
+        if let Some(label) = labels[id] {
+            return label;
+        }
+
+        if self.boxes[id].len() == 1 {
+            return 'O';
+        }
+
+        let left = x > 0 && self.tiles[y][x - 1] == Tile::Box(id);
+        let right = self.tiles[y].get(x + 1) == Some(&Tile::Box(id));
+
+        match (left, right) {
+            (false, _) => '[',
+            (true, true) => '=',
+            (true, false) => ']',
+        }
     }
 }
 
@@ -306,56 +499,213 @@ impl TryFrom<&str> for Warehouse {
     type Error = &'static str;
 
     fn try_from(value: &str) -> Result<Self, Self::Error> {
-        let mut num_boxes = 0u64;
-        let mut num_walls = 0u64;
-
-        let mut robot = (0usize, 0usize);
-
-        let tiles = value.lines().enumerate()
-            .map(|(i, line)| {
-                line.chars().enumerate().map(|(j, c)| {
-                    // update stats
-                    if c == '#' {
-                        num_walls += 1;
-                    } else if c == 'O' {
-                        num_boxes += 1;
-                    }
+        let mut robot = None;
+        let mut boxes = Vec::new();
+
+        // Tiles of boxes spanning several rows, with their letters.
+        let mut labelled = Vec::new();
+
+        let mut tiles = Vec::new();
+
+        for (i, line) in value.lines().enumerate() {
+            let mut row = Vec::new();
+
+            // Whether the previous tile is a box that continues to the right.
+            let mut open = false;
+
+            for (j, c) in line.chars().enumerate() {
+                if open && !matches!(c, '=' | ']') {
+                    return Err("box is missing its right end");
+                }
+
+                let tile = match c {
+                    '.' => Tile::Empty,
+                    '#' => Tile::Wall,
+                    '@' => {
+                        if robot.replace((i, j)).is_some() {
+                            return Err("more than one robot");
+                        }
 
-                    if let Ok(tile) = c.try_into() {
-                        tile
-                    } else {
-                        robot = (i, j);
                         Tile::Empty
-                    }
-                })
-                .collect::<Vec<_>>()
-            })
-            .collect::<Vec<_>>();
+                    },
+                    'O' | '[' => {
+                        boxes.push(vec![(i, j)]);
+                        Tile::Box(boxes.len() - 1)
+                    },
+                    '=' | ']' => match row.last() {
+                        Some(&Tile::Box(id)) if open => {
+                            boxes[id].push((i, j));
+                            Tile::Box(id)
+                        },
+                        _ => return Err("box is missing its left end"),
+                    },
+                    c if c.is_ascii_alphabetic() => {
+                        labelled.push(((i, j), c));
+                        Tile::Empty
+                    },
+                    _ => return Err("unknown tile"),
+                };
+
+                open = matches!(c, '[' | '=');
 
-        Ok(Warehouse {
+                row.push(tile);
+            }
+
+            if open {
+                return Err("box is missing its right end");
+            }
+
+            tiles.push(row);
+        }
+
+        let robot = robot.ok_or("no robot")?;
+
+        let width = tiles.first().map_or(0, Vec::len);
+
+        if tiles.iter().any(|row| row.len() != width) {
+            return Err("rows differ in length");
+        }
+
+        // Walls all around also mean that a step never leaves the map.
+        let enclosed = width > 0 &&
+            tiles.iter().all(|row| row[0] == Tile::Wall && row[width - 1] == Tile::Wall) &&
+            tiles[0].iter().chain(&tiles[tiles.len() - 1]).all(|&tile| tile == Tile::Wall);
+
+        if !enclosed {
+            return Err("warehouse isn't enclosed by walls");
+        }
+
+        let mut warehouse = Warehouse {
             robot,
             tiles,
-        })
+            boxes,
+            history: Vec::new(),
+            future: Vec::new(),
+            changes: Vec::new(),
+        };
+
+        // Each box drawn as a letter covers the tiles with that letter that
+        // can be reached from each other without leaving it.
+        while let Some(&((y, x), c)) = labelled.first() {
+            let mut cells = vec![(y, x)];
+            let mut frontier = vec![(y, x)];
+
+            labelled.remove(0);
+
+            while let Some((y, x)) = frontier.pop() {
+                for next in [(y - 1, x), (y + 1, x), (y, x - 1), (y, x + 1)] {
+                    if let Some(k) = labelled.iter().position(|&tile| tile == (next, c)) {
+                        labelled.remove(k);
+                        cells.push(next);
+                        frontier.push(next);
+                    }
+                }
+            }
+
+            warehouse.add_box(cells)?;
+        }
+
+        warehouse.check()?;
+
+        Ok(warehouse)
     }
 }
 
 impl std::fmt::Display for Warehouse {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let labels = self.labels();
+
         for i in 0..self.tiles.len() {
-            for j in 0..self.tiles[0].len() {
-                write!(f, "{}", if self.robot == (i, j) {
-                    '@'
-                } else {
-                    self.tiles[i][j].try_into().unwrap()
+            for j in 0..self.tiles[i].len() {
+                write!(f, "{}", match self.tiles[i][j] {
+                    _ if self.robot == (i, j) => '@',
+                    Tile::Box(id) => self.box_glyph(&labels, id, (i, j)),
+                    Tile::Empty => '.',
+                    Tile::Wall => '#',
                 })?;
             }
-            writeln!(f, "")?;
+            writeln!(f)?;
         }
 
         Ok(())
     }
 }
 
+#[derive(Clone, Copy, Debug)]
+enum Key {
+    Command(Command),
+    Undo,
+    Redo,
+    Quit,
+}
+
+/// Reads keys from a line typed on a terminal: `^v<>`, arrow keys (which
+/// arrive as escape sequences), `u` to undo, `r` to redo, and `q` to quit.
+/// Anything else is ignored.
+fn parse_keys(line: &str) -> Vec<Key> {
+    let mut keys = Vec::new();
+    let mut chars = line.chars();
+
+    while let Some(c) = chars.next() {
+        let key = match c {
+            '\x1b' => {
+                if chars.next() != Some('[') {
+                    continue;
+                }
+
+                match chars.next() {
+                    Some('A') => Key::Command(Command::Up),
+                    Some('B') => Key::Command(Command::Down),
+                    Some('C') => Key::Command(Command::Right),
+                    Some('D') => Key::Command(Command::Left),
+                    _ => continue,
+                }
+            },
+            'u' => Key::Undo,
+            'r' => Key::Redo,
+            'q' => Key::Quit,
+            c => match Command::try_from(c) {
+                Ok(command) => Key::Command(command),
+                Err(_) => continue,
+            },
+        };
+
+        keys.push(key);
+    }
+
+    keys
+}
+
+/// Lets the robot be moved by hand: every line read from `input` is a
+/// sequence of keys (see `parse_keys`), after which the warehouse and its
+/// GPS sum are shown again.
+fn play(warehouse: &mut Warehouse, input: impl BufRead) {
+    let mut lines = input.lines();
+
+    loop {
+        // Clear the screen and move the cursor to the top left.
+        print!("\x1b[2J\x1b[H{}", warehouse);
+        println!(
+            "GPS: {}, steps: {} (^v<> or arrows, [u]ndo, [r]edo, [q]uit, then enter)",
+            warehouse.sum_gps_coordinates(),
+            warehouse.history.len(),
+        );
+
+        let Some(Ok(line)) = lines.next() else {
+            return;
+        };
+
+        for key in parse_keys(&line) {
+            match key {
+                Key::Command(command) => warehouse.step(command),
+                Key::Undo => { warehouse.undo(); },
+                Key::Redo => { warehouse.redo(); },
+                Key::Quit => return,
+            }
+        }
+    }
+}
+
 fn main() {
     let parts = INPUT.split("\n\n").collect::<Vec<_>>();
 
@@ -375,6 +725,48 @@ fn main() {
     attempts.iter().for_each(|attempt| warehouse.step(*attempt));
 
     println!("{}", warehouse.sum_gps_coordinates());
+
+    // e.g., `cargo run --bin day15 -- --trace`, `-- --replay 100` to see
+    // the wide warehouse after the first 100 steps, or
+    // `-- --play src/bin/day15-sample1.txt --wide --save moves.txt`
+    let args = match Args::parse(std::env::args().skip(1), &["--play", "--save", "--replay"]) {
+        Ok(args) => args,
+        Err(e) => {
+            println!("error: {}", e);
+            return;
+        },
+    };
+
+    if let Some(path) = args.value("--play") {
+        let input = std::fs::read_to_string(path).unwrap();
+        let map = input.split("\n\n").next().unwrap();
+
+        let mut warehouse: Warehouse = map.try_into().unwrap();
+
+        if args.has("--wide") {
+            warehouse.expand();
+        }
+
+        play(&mut warehouse, std::io::stdin().lock());
+
+        if let Some(path) = args.value("--save") {
+            std::fs::write(path, warehouse.command_log(1000)).unwrap();
+        }
+
+        return;
+    }
+
+    if args.has("--trace") {
+        print!("{}", warehouse.trace());
+    }
+
+    if let Some(n) = args.value("--replay") {
+        let n = n.parse::<usize>().expect("expected a number of steps");
+
+        warehouse.replay_to(n).unwrap();
+
+        print!("{}", warehouse);
+    }
 }
 
 #[cfg(test)]
@@ -383,7 +775,7 @@ mod test {
 
     #[test]
     fn test_fmt() {
-        assert_eq!(format!("{}", Tile::Box), "O");
+        assert_eq!(format!("{}", Tile::Box(0)), "O");
     }
 
     #[test]
@@ -400,7 +792,7 @@ mod test {
 
         let warehouse: Result<Warehouse, _> = WAREHOUSE.try_into();
 
-        assert!(matches!(warehouse, Ok(_)));
+        assert!(warehouse.is_ok());
 
         let warehouse = warehouse.unwrap();
 
@@ -543,4 +935,227 @@ mod test {
 ")
         );
     }
+
+    #[test]
+    fn test_undo_redo() {
+        let parts = include_str!("./day15-sample.txt").split("\n\n").collect::<Vec<_>>();
+
+        let commands = parts[1].chars()
+            .filter_map(|c| c.try_into().ok())
+            .collect::<Vec<Command>>();
+
+        let mut warehouse: Warehouse = parts[0].try_into().unwrap();
+
+        warehouse.expand();
+
+        let initial = warehouse.to_string();
+        let mut frames = vec![initial.clone()];
+
+        for command in &commands {
+            warehouse.step(*command);
+            frames.push(warehouse.to_string());
+        }
+
+        assert_eq!(warehouse.sum_gps_coordinates(), 9021);
+
+        for (n, frame) in frames.iter().enumerate().rev() {
+            warehouse.replay_to(n).unwrap();
+            assert_eq!(&warehouse.to_string(), frame);
+        }
+
+        assert!(!warehouse.undo());
+
+        warehouse.replay_to(commands.len()).unwrap();
+
+        assert_eq!(warehouse.sum_gps_coordinates(), 9021);
+        assert!(warehouse.replay_to(commands.len() + 1).is_err());
+
+        // A new step drops whatever was undone.
+        warehouse.replay_to(1).unwrap();
+        warehouse.step(commands[0]);
+
+        assert!(!warehouse.redo());
+    }
+
+    #[test]
+    fn test_trace() {
+        const WAREHOUSE: &str = "\
+#######
+#...#.#
+#.....#
+#..OO@#
+#..O..#
+#.....#
+#######";
+
+        let mut warehouse: Warehouse = WAREHOUSE.try_into().unwrap();
+
+        warehouse.expand();
+
+        for c in "<vv<<^^<<^".chars() {
+            warehouse.step(c.try_into().unwrap());
+        }
+
+        assert_eq!(warehouse.trace(), "\
+1 < (3, 10) -> (3, 9) pushed (3, 6) (3, 8)
+2 v (3, 9) -> (4, 9)
+3 v (4, 9) -> (5, 9)
+4 < (5, 9) -> (5, 8)
+5 < (5, 8) -> (5, 7)
+6 ^ (5, 7) -> (4, 7) pushed (3, 5) (3, 7) (4, 6)
+7 ^ (4, 7) -> (4, 7) blocked
+8 < (4, 7) -> (4, 6)
+9 < (4, 6) -> (4, 5)
+10 ^ (4, 5) -> (3, 5)
+");
+    }
+
+    #[test]
+    fn test_parse_boxes() {
+        let warehouse: Warehouse = "##########\n#O[]@[==]#\n##########".try_into().unwrap();
+
+        assert_eq!(warehouse.boxes, vec![vec![(1, 1)], vec![(1, 2), (1, 3)], vec![(1, 5), (1, 6), (1, 7), (1, 8)]]);
+        assert_eq!(warehouse.to_string(), "##########\n#O[]@[==]#\n##########\n");
+
+        let parse = |row: &str| Warehouse::try_from(format!("#####\n{}\n#####", row).as_str()).err();
+
+        assert_eq!(parse("#[.]#"), Some("box is missing its right end"));
+        assert_eq!(parse("#[]]#"), Some("box is missing its left end"));
+        assert_eq!(parse("#.=]#"), Some("box is missing its left end"));
+        assert_eq!(parse("#@.[#"), Some("box is missing its right end"));
+    }
+
+    #[test]
+    fn test_validation() {
+        let parse = |map: &str| Warehouse::try_from(map).err();
+
+        assert_eq!(parse("#####\n#@.@#\n#####"), Some("more than one robot"));
+        assert_eq!(parse("#####\n#...#\n#####"), Some("no robot"));
+        assert_eq!(parse("#####\n#@+.#\n#####"), Some("unknown tile"));
+        assert_eq!(parse("#####\n#@..#\n####"), Some("rows differ in length"));
+        assert_eq!(parse("#####\n#@...\n#####"), Some("warehouse isn't enclosed by walls"));
+        assert_eq!(parse("##.##\n#@..#\n#####"), Some("warehouse isn't enclosed by walls"));
+        assert_eq!(parse(""), Some("no robot"));
+
+        let mut warehouse: Warehouse = "######\n#@[].#\n######".try_into().unwrap();
+
+        assert_eq!(warehouse.check(), Ok(()));
+
+        warehouse.tiles[1][4] = Tile::Box(0);
+
+        assert_eq!(warehouse.check(), Err("number of box tiles has changed"));
+
+        warehouse.tiles[1][3] = Tile::Empty;
+        warehouse.boxes[0] = vec![(1, 2), (1, 4)];
+
+        assert_eq!(warehouse.check(), Err("box is split within a row"));
+
+        warehouse.robot = (0, 0);
+
+        assert_eq!(warehouse.check(), Err("robot isn't on an empty tile"));
+    }
+
+    #[test]
+    fn test_push_shapes() {
+        const WAREHOUSE: &str = "\
+##########
+#........#
+#........#
+#...[==].#
+#......a.#
+#......aa#
+#......@.#
+##########";
+
+        // An L-shaped box under the right end of the 4x1 box.
+        let mut warehouse: Warehouse = WAREHOUSE.try_into().unwrap();
+
+        assert_eq!(warehouse.boxes[1], vec![(4, 7), (5, 7), (5, 8)]);
+
+        assert!(warehouse.add_box(vec![(6, 7)]).is_err());
+        assert_eq!(warehouse.add_box(vec![(1, 1), (2, 2)]), Err("box isn't in one piece"));
+        assert_eq!(warehouse.add_box(vec![(1, 1), (1, 3), (2, 1), (2, 2), (2, 3)]), Err("box is split within a row"));
+
+        let initial = warehouse.to_string();
+
+        assert_eq!(initial, WAREHOUSE.to_string() + "\n");
+
+        for c in "^^^>".chars() {
+            warehouse.step(c.try_into().unwrap());
+        }
+
+        assert_eq!(warehouse.to_string(), "\
+##########
+#...[==].#
+#......a.#
+#......aa#
+#.......@#
+#........#
+#........#
+##########
+");
+
+        assert_eq!(warehouse.trace(), "\
+1 ^ (6, 7) -> (5, 7) pushed (3, 4) (4, 7)
+2 ^ (5, 7) -> (4, 7) pushed (2, 4) (3, 7)
+3 ^ (4, 7) -> (4, 7) blocked
+4 > (4, 7) -> (4, 8)
+");
+
+        assert_eq!(warehouse.sum_gps_coordinates(), 104 + 207);
+
+        warehouse.replay_to(0).unwrap();
+
+        assert_eq!(warehouse.to_string(), initial);
+        assert_eq!(warehouse.boxes[1], vec![(4, 7), (5, 7), (5, 8)]);
+    }
+
+    #[test]
+    fn test_labels() {
+        // Two touching L shapes, and two vertical 1x2 boxes that share a
+        // letter with the first L shape, since they don't touch it.
+        const WAREHOUSE: &str = "\
+##########
+#a.b..a..#
+#aabb.a..#
+#.@......#
+#....a...#
+#....a...#
+##########
+";
+
+        let warehouse: Warehouse = WAREHOUSE.try_into().unwrap();
+
+        assert_eq!(warehouse.boxes.len(), 4);
+        assert_eq!(warehouse.boxes[1], vec![(1, 3), (2, 3), (2, 4)]);
+        assert_eq!(warehouse.to_string(), WAREHOUSE);
+
+        // Wider boxes keep their letters, and stay in one piece.
+        let mut wide: Warehouse = WAREHOUSE.try_into().unwrap();
+
+        wide.expand();
+
+        let round_trip: Warehouse = wide.to_string().as_str().try_into().unwrap();
+
+        assert_eq!(round_trip.to_string(), wide.to_string());
+        assert_eq!(round_trip.sum_gps_coordinates(), wide.sum_gps_coordinates());
+
+        let parse = |map: &str| Warehouse::try_from(map).err();
+
+        assert_eq!(parse("######\n#a.a@#\n######"), None);
+        assert_eq!(parse("######\n#a.a@#\n#aaa.#\n######"), Some("box is split within a row"));
+    }
+
+    #[test]
+    fn test_play() {
+        let mut warehouse: Warehouse = "#######\n#.@O..#\n#######".try_into().unwrap();
+
+        assert_eq!(parse_keys("\x1b[C>x<\x1b[Du").len(), 5);
+
+        play(&mut warehouse, "\x1b[C>\n<<u\nq\n>>>".as_bytes());
+
+        assert_eq!(warehouse.to_string(), "#######\n#..@.O#\n#######\n");
+        assert_eq!(warehouse.sum_gps_coordinates(), 105);
+        assert_eq!(warehouse.command_log(2), ">>\n<\n");
+    }
 }
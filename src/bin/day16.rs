@@ -1,4 +1,6 @@
-use std::{collections::{BinaryHeap, HashMap, HashSet, VecDeque}, usize};
+use std::collections::{HashMap, HashSet};
+
+use aoc2024::{Args, search::{self, ShortestPaths}};
 
 const INPUT: &str = include_str!("./day16.txt");
 
@@ -59,22 +61,59 @@ impl Node {
     }
 }
 
-#[derive(PartialEq, Eq, Debug)]
-struct Edge {
-    dst: Node,
-    cost: usize,
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+enum Move {
+    Forward,
+    TurnLeft,
+    TurnRight,
 }
 
-impl PartialOrd for Edge {
-    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
-        Some(self.cmp(other))
+impl Move {
+    /// The move that takes the reindeer from `a` to `b`, if any.
+    fn between(a: &Node, b: &Node) -> Option<Move> {
+        if *b == a.rotate_left() {
+            Some(Move::TurnLeft)
+        } else if *b == a.rotate_right() {
+            Some(Move::TurnRight)
+        } else if a.dir == b.dir && a.p != b.p {
+            Some(Move::Forward)
+        } else {
+            None
+        }
     }
 }
 
-impl Ord for Edge {
-    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
-        other.cost.cmp(&self.cost)
+/// Writes moves compactly, e.g., `3F R 12F L F`.
+fn format_moves(moves: &[Move]) -> String {
+    let mut parts = Vec::new();
+    let mut i = 0;
+
+    while i < moves.len() {
+        match moves[i] {
+            Move::Forward => {
+                let n = moves[i..].iter().take_while(|&&m| m == Move::Forward).count();
+
+                parts.push(if n == 1 { "F".to_string() } else { format!("{}F", n) });
+                i += n;
+            },
+            Move::TurnLeft => {
+                parts.push("L".to_string());
+                i += 1;
+            },
+            Move::TurnRight => {
+                parts.push("R".to_string());
+                i += 1;
+            },
+        }
     }
+
+    parts.join(" ")
+}
+
+#[derive(PartialEq, Eq, Debug)]
+struct Edge {
+    dst: Node,
+    cost: usize,
 }
 
 struct Maze {
@@ -84,40 +123,127 @@ struct Maze {
 }
 
 impl Maze {
-    fn dijkstra(&self) -> (HashMap<Node, usize>, HashMap<Node, HashSet<Node>>) {
-        let mut pq: BinaryHeap<Edge> = BinaryHeap::new();
-        let mut dist = HashMap::new();
-        let mut prev = HashMap::new();
-
-        dist.insert(self.src, 0);
-        prev.insert(self.src, HashSet::new());
-        pq.push(Edge { dst: self.src, cost: 0 });
-
-        while let Some(Edge { dst: u, cost: _ }) = pq.pop() {
-            if let Some(neighbors) = self.adj_list.get(&u) {
-                for &Edge { dst: v, cost } in neighbors {
-                    let alt = dist.get(&u).unwrap() + cost;
+    fn successors(&self, node: &Node) -> impl Iterator<Item = (Node, usize)> + '_ {
+        self.adj_list.get(node)
+            .into_iter()
+            .flatten()
+            .map(|edge| (edge.dst, edge.cost))
+    }
 
-                    let d = *dist.get(&v).unwrap_or(&usize::MAX);
+    fn dijkstra(&self) -> ShortestPaths<Node> {
+        search::shortest_path_dag([self.src], |node| self.successors(node))
+    }
 
-                    if alt == d {
-                        prev.entry(v).or_insert(HashSet::new()).insert(u);
-                    } else if alt < d {
-                        dist.entry(v).and_modify(|d| *d = alt).or_insert(alt);
+    fn exit_nodes(&self) -> impl Iterator<Item = Node> + '_ {
+        Dir::dirs().into_iter().map(|dir| Node { p: self.exit, dir })
+    }
 
-                        pq.push(Edge { dst: v, cost: alt });
+    /// Cost of the cheapest way from every node to the exit, found by
+    /// searching backwards along every edge.
+    fn dist_to_exit(&self) -> HashMap<Node, usize> {
+        let mut rev_adj_list: HashMap<Node, Vec<Edge>> = HashMap::new();
 
-                        prev.insert(v, {
-                            let mut s = HashSet::new();
-                            s.insert(u);
-                            s
-                        });
-                    }
-                }
+        for (&src, edges) in &self.adj_list {
+            for edge in edges {
+                rev_adj_list.entry(edge.dst)
+                    .or_default()
+                    .push(Edge { dst: src, cost: edge.cost });
             }
         }
 
-        (dist, prev)
+        search::dijkstra(self.exit_nodes(), |node| {
+            rev_adj_list.get(node)
+                .into_iter()
+                .flatten()
+                .map(|edge| (edge.dst, edge.cost))
+        })
+        .dist
+    }
+
+    /// Steps to a neighbouring tile, each as the node the reindeer ends up
+    /// at and the turns it takes on the spot beforehand.
+    fn steps(&self, node: &Node) -> Vec<(Node, Vec<Move>)> {
+        let turns = [
+            vec![],
+            vec![Move::TurnLeft],
+            vec![Move::TurnRight],
+            vec![Move::TurnRight, Move::TurnRight],
+        ];
+
+        turns.into_iter()
+            .filter_map(|turns| {
+                let facing = turns.iter().fold(*node, |node, turn| match turn {
+                    Move::TurnLeft => node.rotate_left(),
+                    _ => node.rotate_right(),
+                });
+
+                let dst = self.successors(&facing)
+                    .map(|(dst, _)| dst)
+                    .find(|dst| Move::between(&facing, dst) == Some(Move::Forward))?;
+
+                Some((dst, turns))
+            })
+            .collect()
+    }
+
+    /// The `k` cheapest routes to the exit that don't pass the same tile
+    /// twice, as moves, each with its score.
+    fn best_routes(&self, k: usize) -> Vec<(Vec<Move>, usize)> {
+        // The exact cost to the exit is the best possible A* heuristic.
+        let dist_to_exit = self.dist_to_exit();
+
+        // Routes step from tile to tile, so that turning on the spot can't
+        // make a route of its own.
+        let paths = search::k_shortest_paths_by(
+            self.src,
+            |node| {
+                self.steps(node).into_iter()
+                    .map(|(dst, turns)| (dst, 1000 * turns.len() + 1))
+                    .collect::<Vec<_>>()
+            },
+            |node| dist_to_exit.get(node).copied().unwrap_or(usize::MAX / 2),
+            |node| node.p == self.exit,
+            |node| node.p,
+            k,
+        );
+
+        paths.into_iter()
+            .map(|(path, cost)| {
+                let moves = path.windows(2)
+                    .flat_map(|pair| {
+                        let (_, turns) = self.steps(&pair[0]).into_iter()
+                            .find(|(dst, _)| *dst == pair[1])
+                            .unwrap();
+
+                        turns.into_iter().chain([Move::Forward])
+                    })
+                    .collect();
+
+                (moves, cost)
+            })
+            .collect()
+    }
+
+    /// Tiles the reindeer can pass while scoring at most `slack` more than
+    /// the best score. With no slack, these are the tiles on best paths.
+    fn tiles_within(&self, slack: usize) -> HashSet<(usize, usize)> {
+        let dist_from_src = self.dijkstra().dist;
+        let dist_to_exit = self.dist_to_exit();
+
+        let best = self.exit_nodes()
+            .filter_map(|node| dist_from_src.get(&node).copied())
+            .min();
+
+        let Some(best) = best else {
+            return HashSet::new();
+        };
+
+        dist_from_src.iter()
+            .filter(|(node, &d)| {
+                dist_to_exit.get(node).is_some_and(|&rest| d + rest <= best + slack)
+            })
+            .map(|(node, _)| node.p)
+            .collect()
     }
 }
 
@@ -186,59 +312,100 @@ impl TryFrom<&str> for Maze {
 fn find_pos_on_shortest_path(
     maze: &Maze,
     len_shortest_path: usize,
-    dist: &HashMap<Node, usize>,
-    prev: &HashMap<Node, HashSet<Node>>,
+    paths: &ShortestPaths<Node>,
 ) -> HashSet<(usize, usize)> {
-    let mut nodes_on_shortest_path: HashSet<Node> = HashSet::new();
-
-    for dir in Dir::dirs() {
-        let maybe_exit_node = Node { p: maze.exit, dir };
+    let exits = maze.exit_nodes()
+        .filter(|node| paths.dist.get(node) == Some(&len_shortest_path));
 
-        if *dist.get(&maybe_exit_node).unwrap() == len_shortest_path {
-            nodes_on_shortest_path.insert(maybe_exit_node);
-
-            let mut q = VecDeque::new();
-            q.push_front(maybe_exit_node);
-
-            while !q.is_empty() {
-                if let Some(n) = q.pop_back() {
-                    for &p in prev.get(&n).unwrap() {
-                        if !nodes_on_shortest_path.contains(&p) {
-                            nodes_on_shortest_path.insert(p);
-                            q.push_front(p);
-                        }
-                    }
-                }
-            }
-        }
-    }
-
-    nodes_on_shortest_path.into_iter()
+    paths.nodes_on_paths_to(exits).into_iter()
         .map(|n| n.p)
         .collect::<HashSet<_>>()
 }
 
 fn main() {
+    // e.g., `cargo run --bin day16 -- --routes 3`, or `-- --slack 1000`
+    let args = match Args::parse(std::env::args().skip(1), &["--routes", "--slack"]) {
+        Ok(args) => args,
+        Err(e) => {
+            println!("error: {}", e);
+            return;
+        },
+    };
+
     let maze: Maze = INPUT.try_into().unwrap();
 
-    let (dist, prev) = maze.dijkstra();
+    let paths = maze.dijkstra();
 
-    let len_shortest_path = dist.iter()
-        .filter_map(|(&n, &d)| {
-            if n.p == maze.exit {
-                Some(d)
-            } else {
-                None
-            }
-        })
+    let len_shortest_path = maze.exit_nodes()
+        .filter_map(|node| paths.dist.get(&node).copied())
         .min()
         .unwrap();
 
     println!("{}", len_shortest_path);
 
     let pos_on_shortest_path = find_pos_on_shortest_path(
-        &maze, len_shortest_path, &dist, &prev
+        &maze, len_shortest_path, &paths
     );
 
     println!("{}", pos_on_shortest_path.len());
+
+    if let Some(k) = args.value("--routes") {
+        for (moves, score) in maze.best_routes(k.parse().unwrap()) {
+            println!("{}: {}", score, format_moves(&moves));
+        }
+    }
+
+    if let Some(slack) = args.value("--slack") {
+        println!("{}", maze.tiles_within(slack.parse().unwrap()).len());
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_samples() {
+        for (input, best, tiles, (slack, more_tiles)) in [
+            (include_str!("./day16-sample.txt"), 7036, 45, (4000, 66)),
+            (include_str!("./day16-sample2.txt"), 11048, 64, (1000, 86)),
+        ] {
+            let maze: Maze = input.try_into().unwrap();
+
+            let routes = maze.best_routes(3);
+
+            assert_eq!(routes[0].1, best);
+            assert!(routes.windows(2).all(|pair| pair[0].1 <= pair[1].1 && pair[0].0 != pair[1].0));
+
+            assert_eq!(maze.tiles_within(0).len(), tiles);
+            assert_eq!(maze.tiles_within(slack).len(), more_tiles);
+        }
+    }
+
+    #[test]
+    fn test_routes() {
+        const MAZE: &str = "\
+#####
+#..E#
+#.#.#
+#S..#
+#####";
+
+        let maze: Maze = MAZE.try_into().unwrap();
+
+        // There are only two ways around the wall: turning on the spot,
+        // e.g., right three times instead of left once, is no third.
+        let routes = maze.best_routes(3)
+            .into_iter()
+            .map(|(moves, score)| (format_moves(&moves), score))
+            .collect::<Vec<_>>();
+
+        assert_eq!(routes, vec![
+            ("2F L 2F".to_string(), 1004),
+            ("L 2F R 2F".to_string(), 2004),
+        ]);
+
+        assert_eq!(maze.tiles_within(0).len(), 5);
+        assert_eq!(maze.tiles_within(1000).len(), 8);
+    }
 }
@@ -1,4 +1,4 @@
-use std::collections::{HashMap, VecDeque};
+use aoc2024::search;
 
 const INPUT: &str = include_str!("./day18.txt");
 
@@ -17,44 +17,37 @@ impl<const N: usize> Ram<N> {
         Self([[Byte::Safe; N]; N])
     }
 
-    fn capacity(&self) -> usize {
-        N
-    }
-
     fn corrupt(&mut self, y: usize, x: usize) {
         self.0[y][x] = Byte::Corrupted;
     }
 
-    fn bfs(&self) -> HashMap<(usize, usize), usize>{
-        let mut dist = HashMap::new();
-        let mut q = VecDeque::new();
-
-        dist.insert((0, 0), 0);
-        q.push_front((0, 0));
-
-        while !q.is_empty() {
-            let u = q.pop_back().unwrap();
-            let d = *dist.get(&u).unwrap();
-
-            'next_dir: for &(dy, dx) in DIRS.iter() {
-                let (y, x) = (u.0 as i32 + dy, u.1 as i32 + dx);
-                if y < 0 || N <= y as usize || x < 0 || N <= x as usize {
-                    continue 'next_dir;
-                }
-
-                let (y, x) = (y as usize, x as usize);
-                if self.0[y][x] == Byte::Corrupted {
-                    continue 'next_dir;
-                }
+    fn successors(&self, (y, x): (usize, usize)) -> impl Iterator<Item = ((usize, usize), usize)> + '_ {
+        DIRS.iter().filter_map(move |&(dy, dx)| {
+            let (y, x) = (y as i32 + dy, x as i32 + dx);
+            if y < 0 || N <= y as usize || x < 0 || N <= x as usize {
+                return None;
+            }
 
-                if let None = dist.get(&(y, x)) {
-                    dist.insert((y, x), d + 1);
-                    q.push_front((y, x));
-                }
+            let (y, x) = (y as usize, x as usize);
+            if self.0[y][x] == Byte::Corrupted {
+                return None;
             }
-        }
 
-        dist
+            Some(((y, x), 1))
+        })
+    }
+
+    /// Number of steps from the top left corner to the bottom right one.
+    fn shortest_path(&self) -> Option<usize> {
+        let exit = (N - 1, N - 1);
+
+        search::astar(
+            (0, 0),
+            |&p| self.successors(p),
+            |&(y, x)| exit.0 - y + exit.1 - x,
+            |&p| p == exit,
+        )
+        .map(|(_, steps)| steps)
     }
 }
 
@@ -77,8 +70,6 @@ impl<const N: usize> std::fmt::Display for Ram<N> {
 fn main() {
     let mut ram: Ram<71> = Ram::new();
 
-    let n = ram.capacity();
-
     // part 1
     INPUT.lines().take(1024).for_each(|line| {
         let parts = line.split(",").collect::<Vec<_>>();
@@ -89,9 +80,7 @@ fn main() {
         ram.corrupt(y, x);
     });
 
-    let dist = ram.bfs();
-
-    println!("{}", dist.get(&(n - 1, n - 1)).unwrap());
+    println!("{}", ram.shortest_path().unwrap());
 
     // part 2
     let bytes_falling = INPUT.lines().map(|line| {
@@ -108,12 +97,11 @@ fn main() {
     let p = indices.partition_point(|&k| {
         let mut ram: Ram<71> = Ram::new();
 
-        for i in 0..k {
-            let (y, x) = bytes_falling[i];
+        for &(y, x) in &bytes_falling[..k] {
             ram.corrupt(y, x);
         }
 
-        ram.bfs().get(&(n - 1, n - 1)).is_some()
+        ram.shortest_path().is_some()
     });
 
     println!("{},{}", bytes_falling[p - 1].1, bytes_falling[p - 1].0);
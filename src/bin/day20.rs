@@ -1,4 +1,6 @@
-use std::collections::{HashMap, VecDeque};
+use std::collections::HashMap;
+
+use aoc2024::search;
 
 const INPUT: &str = include_str!("./day20.txt");
 
@@ -25,41 +27,26 @@ struct Cheat {
 }
 
 impl RaceTrack {
-    fn bfs(&self, src: (usize, usize)) -> HashMap<(usize, usize), usize> {
-        let mut dist = HashMap::<(usize, usize), usize>::new();
-        dist.insert(src.clone(), 0);
-
-        let mut q = VecDeque::<(usize, usize)>::new();
-        q.push_front(src.clone());
-
-        while !q.is_empty() {
-            let (y, x) = q.pop_back().unwrap();
-
-            let d = *dist.get(&(y, x)).unwrap();
-
-            for &(dy, dx) in DIRS {
-                let (y1, x1) = (
-                    (y as i32 + dy) as usize,
-                    (x as i32 + dx) as usize,
-                );
-
-                match self.tiles[y1][x1] {
-                    Tile::Track => {
-                        if !dist.contains_key(&(y1, x1)) {
-                            dist.insert((y1, x1), d + 1);
-                            q.push_front((y1, x1));
-                        }
-                    },
-                    Tile::Wall => {},
-                }
+    fn successors(&self, (y, x): (usize, usize)) -> impl Iterator<Item = ((usize, usize), usize)> + '_ {
+        DIRS.iter().filter_map(move |&(dy, dx)| {
+            let (y1, x1) = (
+                (y as i32 + dy) as usize,
+                (x as i32 + dx) as usize,
+            );
+
+            match self.tiles[y1][x1] {
+                Tile::Track => Some(((y1, x1), 1)),
+                Tile::Wall => None,
             }
-        }
+        })
+    }
 
-        dist
+    fn distances_from(&self, src: (usize, usize)) -> HashMap<(usize, usize), usize> {
+        search::dijkstra([src], |&p| self.successors(p)).dist
     }
 
     fn populate_dist_map(&mut self)  {
-        self.dist_map = self.bfs(self.end);
+        self.dist_map = self.distances_from(self.end);
         self.elapsed_ps_no_cheat = *self.dist_map.get(&self.start).unwrap();
     }
 
@@ -95,17 +82,7 @@ impl RaceTrack {
     fn cheats_to_saved_ps(&self, budget: usize) -> HashMap<Cheat, usize> {
         let mut cheats_to_dist = HashMap::<Cheat, usize>::new();
 
-        let mut dist = HashMap::<(usize, usize), usize>::new();
-        dist.insert(self.start, 0);
-
-        let mut q = VecDeque::<(usize, usize)>::new();
-        q.push_front(self.start);
-
-        while !q.is_empty() {
-            let cheat_start = q.pop_back().unwrap();
-
-            let before_cheat = *dist.get(&cheat_start).unwrap();
-
+        for (&cheat_start, &before_cheat) in &self.distances_from(self.start) {
             for len_cheat in 0..=budget {
                 for cheat_end in self.pos_at_dist(cheat_start, len_cheat) {
                     let after_cheat = self.dist_map.get(&cheat_end).unwrap();
@@ -125,23 +102,6 @@ impl RaceTrack {
                     }
                 }
             }
-
-            for &(dy, dx) in DIRS {
-                let (y1, x1) = (
-                    (cheat_start.0 as i32 + dy) as usize,
-                    (cheat_start.1 as i32 + dx) as usize,
-                );
-
-                match self.tiles[y1][x1] {
-                    Tile::Track => {
-                        if !dist.contains_key(&(y1, x1)) {
-                            dist.insert((y1, x1), before_cheat + 1);
-                            q.push_front((y1, x1));
-                        }
-                    },
-                    Tile::Wall => {},
-                }
-            }
         }
 
         cheats_to_dist
@@ -169,8 +129,8 @@ impl TryFrom<&str> for RaceTrack {
 
         input.lines().enumerate().for_each(|(y, line)| {
             line.chars().enumerate().for_each(|(x, c)| {
-                if y <= 0 || y >= tiles.len() - 1 ||
-                    x <= 0 || x >= tiles.len() - 1 {
+                if y == 0 || y >= tiles.len() - 1 ||
+                    x == 0 || x >= tiles.len() - 1 {
                     return;
                 }
 
@@ -1,79 +1,321 @@
+use std::str::FromStr;
+
+#[cfg(feature = "bigint")]
+use num_bigint::BigInt;
+
 const INPUT: &str = include_str!("./day7.txt");
 
-struct Eqn {
-    target: i64,
-    operands: Vec<i64>,
+#[cfg(not(feature = "bigint"))]
+type Value = i64;
+
+#[cfg(feature = "bigint")]
+type Value = BigInt;
+
+const OVERFLOW: &str = "arithmetic overflow";
+
+/// Integer types an equation can be evaluated over. Every operation is
+/// checked and returns `None` if the result doesn't fit in `Self`.
+trait Int: Sized + Clone + Ord + FromStr {
+    fn from_u32(n: u32) -> Self;
+
+    fn is_zero(&self) -> bool;
+
+    fn is_negative(&self) -> bool;
+
+    fn checked_add(&self, rhs: &Self) -> Option<Self>;
+
+    fn checked_sub(&self, rhs: &Self) -> Option<Self>;
+
+    fn checked_mul(&self, rhs: &Self) -> Option<Self>;
+
+    /// Returns `None` on division by zero as well as on overflow.
+    fn checked_div(&self, rhs: &Self) -> Option<Self>;
+
+    fn checked_rem(&self, rhs: &Self) -> Option<Self>;
 }
 
-enum BinOp {
-    Add,
-    Mul,
-    Concat,
+macro_rules! impl_int_for_primitive {
+    ($($t:ty),*) => {
+        $(
+            impl Int for $t {
+                fn from_u32(n: u32) -> Self { n as $t }
+
+                fn is_zero(&self) -> bool { *self == 0 }
+
+                fn is_negative(&self) -> bool { *self < 0 }
+
+                fn checked_add(&self, rhs: &Self) -> Option<Self> {
+                    <$t>::checked_add(*self, *rhs)
+                }
+
+                fn checked_sub(&self, rhs: &Self) -> Option<Self> {
+                    <$t>::checked_sub(*self, *rhs)
+                }
+
+                fn checked_mul(&self, rhs: &Self) -> Option<Self> {
+                    <$t>::checked_mul(*self, *rhs)
+                }
+
+                fn checked_div(&self, rhs: &Self) -> Option<Self> {
+                    <$t>::checked_div(*self, *rhs)
+                }
+
+                fn checked_rem(&self, rhs: &Self) -> Option<Self> {
+                    <$t>::checked_rem(*self, *rhs)
+                }
+            }
+        )*
+    };
+}
+
+impl_int_for_primitive!(i64, i128);
+
+#[cfg(feature = "bigint")]
+impl Int for BigInt {
+    fn from_u32(n: u32) -> Self { BigInt::from(n) }
+
+    fn is_zero(&self) -> bool { self.sign() == num_bigint::Sign::NoSign }
+
+    fn is_negative(&self) -> bool { self.sign() == num_bigint::Sign::Minus }
+
+    fn checked_add(&self, rhs: &Self) -> Option<Self> { Some(self + rhs) }
+
+    fn checked_sub(&self, rhs: &Self) -> Option<Self> { Some(self - rhs) }
+
+    fn checked_mul(&self, rhs: &Self) -> Option<Self> { Some(self * rhs) }
+
+    fn checked_div(&self, rhs: &Self) -> Option<Self> {
+        if rhs.is_zero() { None } else { Some(self / rhs) }
+    }
+
+    fn checked_rem(&self, rhs: &Self) -> Option<Self> {
+        if rhs.is_zero() { None } else { Some(self % rhs) }
+    }
+}
+
+/// A binary operator that can be placed between two operands.
+///
+/// `eval` returns `Ok(None)` if the operator doesn't apply to the given
+/// operands (e.g., inexact division), and `Err` if the result is not
+/// representable in `T`.
+trait BinOp<T: Int> {
+    fn eval(&self, lhs: &T, rhs: &T) -> Result<Option<T>, &'static str>;
+}
+
+struct Add;
+
+struct Sub;
+
+struct Mul;
+
+/// Exact integer division; doesn't apply if `rhs` doesn't divide `lhs`.
+struct Div;
+
+/// Concatenation of digits in the given base, e.g., `12 || 345 = 12345`
+/// in base 10. Doesn't apply to a negative `rhs`; a base below 2 is an error.
+struct Concat {
+    base: u32,
+}
+
+impl<T: Int> BinOp<T> for Add {
+    fn eval(&self, lhs: &T, rhs: &T) -> Result<Option<T>, &'static str> {
+        lhs.checked_add(rhs).map(Some).ok_or(OVERFLOW)
+    }
+}
+
+impl<T: Int> BinOp<T> for Sub {
+    fn eval(&self, lhs: &T, rhs: &T) -> Result<Option<T>, &'static str> {
+        lhs.checked_sub(rhs).map(Some).ok_or(OVERFLOW)
+    }
+}
+
+impl<T: Int> BinOp<T> for Mul {
+    fn eval(&self, lhs: &T, rhs: &T) -> Result<Option<T>, &'static str> {
+        lhs.checked_mul(rhs).map(Some).ok_or(OVERFLOW)
+    }
 }
 
-impl BinOp {
-    fn eval(&self, lhs: i64, rhs: i64) -> i64 {
-        match self {
-            BinOp::Add => lhs + rhs,
-            BinOp::Mul => lhs * rhs,
-            BinOp::Concat => {
-                // FIXME(efficiency :p)
-                format!("{}{}", lhs, rhs).parse::<i64>().unwrap()
-            },
+impl<T: Int> BinOp<T> for Div {
+    fn eval(&self, lhs: &T, rhs: &T) -> Result<Option<T>, &'static str> {
+        if rhs.is_zero() || !lhs.checked_rem(rhs).ok_or(OVERFLOW)?.is_zero() {
+            return Ok(None);
         }
+
+        lhs.checked_div(rhs).map(Some).ok_or(OVERFLOW)
     }
 }
 
-impl Eqn {
-    fn is_sat(&self, binops: &Vec<BinOp>) -> bool {
-        self.operands.iter()
-            .fold(Vec::new(), |acc, &operand| {
-                if acc.len() == 0 {
-                    return vec![operand];
+impl<T: Int> BinOp<T> for Concat {
+    fn eval(&self, lhs: &T, rhs: &T) -> Result<Option<T>, &'static str> {
+        if self.base < 2 {
+            return Err("concatenation base must be at least 2");
+        }
+
+        if rhs.is_negative() {
+            return Ok(None);
+        }
+
+        let base = T::from_u32(self.base);
+
+        let mut shift = base.clone();
+        while shift <= *rhs {
+            shift = shift.checked_mul(&base).ok_or(OVERFLOW)?;
+        }
+
+        lhs.checked_mul(&shift)
+            .and_then(|lhs| lhs.checked_add(rhs))
+            .map(Some)
+            .ok_or(OVERFLOW)
+    }
+}
+
+struct Eqn<T> {
+    target: T,
+    operands: Vec<T>,
+}
+
+impl<T: Int> Eqn<T> {
+    fn is_sat(&self, binops: &[&dyn BinOp<T>]) -> Result<bool, &'static str> {
+        let Some((first, rest)) = self.operands.split_first() else {
+            return Ok(false);
+        };
+
+        let mut values = vec![first.clone()];
+
+        for operand in rest {
+            let mut next = Vec::with_capacity(values.len() * binops.len());
+
+            for value in &values {
+                for binop in binops {
+                    if let Some(value) = binop.eval(value, operand)? {
+                        next.push(value);
+                    }
                 }
+            }
 
-                acc.into_iter()
-                    .flat_map(|value| {
-                        binops.iter()
-                            .map(|binop| binop.eval(value, operand))
-                            .collect::<Vec<_>>()
-                    })
-                    .collect::<Vec<_>>()
-            })
-            .contains(&self.target)
+            values = next;
+        }
+
+        Ok(values.contains(&self.target))
     }
 }
 
-impl TryFrom<&str> for Eqn {
+impl<T: Int> TryFrom<&str> for Eqn<T> {
     type Error = &'static str;
 
     fn try_from(value: &str) -> Result<Self, Self::Error> {
-        let parts = value.split(':').collect::<Vec<_>>();
+        let (target, operands) = value.split_once(':')
+            .ok_or("missing ':' in equation")?;
 
-        let target = parts[0].parse::<i64>().unwrap();
+        let target = target.trim().parse::<T>()
+            .map_err(|_| "invalid target")?;
 
-        let operands = parts[1].split_whitespace()
-            .map(|operand| operand.parse::<i64>().unwrap())
-            .collect::<Vec<_>>();
+        let operands = operands.split_whitespace()
+            .map(|operand| operand.parse::<T>().map_err(|_| "invalid operand"))
+            .collect::<Result<Vec<_>, _>>()?;
 
         Ok(Eqn { target, operands })
     }
 }
 
+fn sum_of_sat_targets<T: Int>(
+    input: &str,
+    binops: &[&dyn BinOp<T>],
+) -> Result<T, &'static str> {
+    let mut sum = T::from_u32(0);
+
+    for line in input.lines() {
+        let eqn = Eqn::<T>::try_from(line)?;
+
+        if eqn.is_sat(binops)? {
+            sum = sum.checked_add(&eqn.target).ok_or(OVERFLOW)?;
+        }
+    }
+
+    Ok(sum)
+}
+
+/// Parses an operator set such as `+*|`, where `|` is base-10 concatenation.
+fn parse_binops<T: Int>(spec: &str) -> Result<Vec<Box<dyn BinOp<T>>>, &'static str> {
+    spec.chars()
+        .map(|c| -> Result<Box<dyn BinOp<T>>, &'static str> {
+            match c {
+                '+' => Ok(Box::new(Add)),
+                '-' => Ok(Box::new(Sub)),
+                '*' => Ok(Box::new(Mul)),
+                '/' => Ok(Box::new(Div)),
+                '|' => Ok(Box::new(Concat { base: 10 })),
+                _ => Err("unknown operator"),
+            }
+        })
+        .collect()
+}
+
 fn main() {
-    let part1 = INPUT.lines()
-        .map(|line| Eqn::try_from(line).unwrap())
-        .filter(|eqn| eqn.is_sat(&vec![BinOp::Add, BinOp::Mul]))
-        .map(|eqn| eqn.target)
-        .sum::<i64>();
+    let part1 = sum_of_sat_targets::<Value>(INPUT, &[&Add, &Mul]).unwrap();
 
     println!("{}", part1);
 
-    let part2 = INPUT.lines()
-        .map(|line| Eqn::try_from(line).unwrap())
-        .filter(|eqn| eqn.is_sat(&vec![BinOp::Add, BinOp::Mul, BinOp::Concat]))
-        .map(|eqn| eqn.target)
-        .sum::<i64>();
+    let part2 = sum_of_sat_targets::<Value>(
+        INPUT,
+        &[&Add, &Mul, &Concat { base: 10 }],
+    ).unwrap();
 
     println!("{}", part2);
-}
\ No newline at end of file
+
+    // e.g., `cargo run --bin day7 -- '+-*/|'`
+    if let Some(spec) = std::env::args().nth(1) {
+        let binops = parse_binops::<Value>(&spec).unwrap();
+        let binops = binops.iter().map(|binop| binop.as_ref()).collect::<Vec<_>>();
+
+        match sum_of_sat_targets::<Value>(INPUT, &binops) {
+            Ok(sum) => println!("{}", sum),
+            Err(e) => println!("error: {}", e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_concat() {
+        let concat = Concat { base: 10 };
+
+        assert_eq!(BinOp::<i64>::eval(&concat, &12, &345), Ok(Some(12345)));
+        assert_eq!(BinOp::<i64>::eval(&concat, &1, &0), Ok(Some(10)));
+
+        let concat = Concat { base: 2 };
+
+        assert_eq!(BinOp::<i64>::eval(&concat, &0b10, &0b11), Ok(Some(0b1011)));
+
+        for base in [0, 1] {
+            let concat = Concat { base };
+
+            assert!(BinOp::<i64>::eval(&concat, &1, &1).is_err());
+        }
+    }
+
+    #[test]
+    fn test_overflow_is_an_error() {
+        let eqn: Eqn<i64> = "1: 9223372036854775807 2".try_into().unwrap();
+
+        assert_eq!(eqn.is_sat(&[&Add]), Err(OVERFLOW));
+
+        let eqn: Eqn<i128> = "1: 9223372036854775807 2".try_into().unwrap();
+
+        assert_eq!(eqn.is_sat(&[&Add]), Ok(false));
+    }
+
+    #[test]
+    fn test_div_and_sub() {
+        let eqn: Eqn<i64> = "4: 10 2 1".try_into().unwrap();
+
+        assert_eq!(eqn.is_sat(&[&Div, &Sub]), Ok(true));
+
+        let eqn: Eqn<i64> = "3: 10 3 1".try_into().unwrap();
+
+        assert_eq!(eqn.is_sat(&[&Div]), Ok(false));
+    }
+}
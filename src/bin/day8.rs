@@ -4,91 +4,186 @@ use itertools::Itertools;
 
 const INPUT: &str = include_str!("./day8.txt");
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy)]
 enum Cell {
     Antinode,
     Antenna(char),
     Empty,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 struct Map {
     cells: Vec<Vec<Cell>>,
     antennas: HashMap<char, Vec<(usize, usize)>>,
 }
 
+/// Where antinodes appear relative to a pair of same-frequency antennas.
+#[derive(Debug, Clone, Copy)]
+enum Rule {
+    /// At the two points in line with the pair where one antenna is twice
+    /// as far away as the other (part 1).
+    Twice,
+    /// At every grid point in line with the pair (part 2).
+    Collinear,
+}
+
+#[derive(Debug)]
+struct Antinode {
+    pos: (usize, usize),
+    freq: char,
+    /// The pair of antennas that produced this antinode.
+    pair: ((usize, usize), (usize, usize)),
+}
+
+fn gcd(a: i64, b: i64) -> i64 {
+    if b == 0 { a.abs() } else { gcd(b, a % b) }
+}
+
 impl Map {
     #[inline]
-    fn is_valid_coord(&self, p: (i64, i64)) -> bool {
-        0 <= p.0 && p.0 < self.cells.len() as i64 &&
-        0 <= p.1 && p.1 < self.cells[0].len() as i64
+    fn height(&self) -> usize {
+        self.cells.len()
     }
 
-    fn antinodes1(
-        &self,
-        p: &(usize, usize),
-        q: &(usize, usize)
-    ) -> Vec<(usize, usize)> {
-        let r = (
-            (2 * (p.0 as i64) - (q.0 as i64)),
-            (2 * (p.1 as i64) - (q.1 as i64)),
-        );
+    #[inline]
+    fn width(&self) -> usize {
+        self.cells.first().map_or(0, |row| row.len())
+    }
 
-        let s = (
-            (2 * (q.0 as i64) - (p.0 as i64)),
-            (2 * (q.1 as i64) - (p.1 as i64)),
-        );
+    #[inline]
+    fn is_valid_coord(&self, p: (i64, i64)) -> bool {
+        0 <= p.0 && p.0 < self.height() as i64 &&
+        0 <= p.1 && p.1 < self.width() as i64
+    }
 
-        let antinodes = vec![r, s].into_iter()
-            .filter(|&p| self.is_valid_coord(p))
+    /// Walks from `p` in steps of `d` (inclusive of `p`) until leaving the map.
+    fn ray(&self, p: (i64, i64), d: (i64, i64)) -> impl Iterator<Item = (usize, usize)> + '_ {
+        std::iter::successors(Some(p), move |p| Some((p.0 + d.0, p.1 + d.1)))
+            .take_while(|&p| self.is_valid_coord(p))
             .map(|p| (p.0 as usize, p.1 as usize))
-            .collect::<Vec<_>>();
-
-        antinodes
     }
 
-    fn antinodes_infinity(
+    fn antinodes_of_pair(
         &self,
-        p: &(usize, usize),
-        q: &(usize, usize)
+        p: (usize, usize),
+        q: (usize, usize),
+        rule: Rule,
     ) -> Vec<(usize, usize)> {
         let p = (p.0 as i64, p.1 as i64);
         let q = (q.0 as i64, q.1 as i64);
+        let d = (q.0 - p.0, q.1 - p.1);
 
-        let left = (0..self.cells.len() as i64)
-            .map(|k| (p.0 + k * (p.0 - q.0), p.1 + k * (p.1 - q.1)))
-            .take_while(|&p| self.is_valid_coord(p))
-            .map(|p| (p.0 as usize, p.1 as usize))
-            .collect::<Vec<_>>();
-
-        let right = (0..self.cells.len() as i64)
-            .map(|k| (q.0 + k * (q.0 - p.0), q.1 + k * (q.1 - p.1)))
-            .take_while(|&p| self.is_valid_coord(p))
-            .map(|p| (p.0 as usize, p.1 as usize))
-            .collect::<Vec<_>>();
+        match rule {
+            Rule::Twice => {
+                [(p.0 - d.0, p.1 - d.1), (q.0 + d.0, q.1 + d.1)].into_iter()
+                    .filter(|&p| self.is_valid_coord(p))
+                    .map(|p| (p.0 as usize, p.1 as usize))
+                    .collect()
+            },
+            Rule::Collinear => {
+                // Reduce the step so that lattice points between (and beyond)
+                // the antennas are not skipped when `d` is not primitive.
+                let g = gcd(d.0, d.1);
+                let d = (d.0 / g, d.1 / g);
 
-        let mut res = left;
-        res.extend(right);
-        res
+                self.ray(p, d)
+                    .chain(self.ray((p.0 - d.0, p.1 - d.1), (-d.0, -d.1)))
+                    .collect()
+            },
+        }
     }
 
-    fn find_unique_antinodes<F>(
-        &self,
-        find_antinodes_for: F,
-    ) -> HashSet<(usize, usize)>
-    where
-        F: Fn(&Self, &(usize, usize), &(usize, usize)) -> Vec<(usize, usize)>
-    {
-        let mut antinodes = HashSet::new();
-
-        for (_, locations) in &self.antennas {
-            locations.iter().combinations(2).for_each(|pair| {
-                antinodes.extend(find_antinodes_for(self, pair[0], pair[1]));
-            });
+    /// Returns every antinode along with the antenna pair that produced it.
+    /// The same position may appear more than once if several pairs (or
+    /// frequencies) produce it.
+    fn antinodes(&self, rule: Rule) -> Vec<Antinode> {
+        let mut antinodes = Vec::new();
+
+        for (&freq, locations) in &self.antennas {
+            for pair in locations.iter().combinations(2) {
+                let pair = (*pair[0], *pair[1]);
+
+                antinodes.extend(
+                    self.antinodes_of_pair(pair.0, pair.1, rule).into_iter()
+                        .map(|pos| Antinode { pos, freq, pair })
+                );
+            }
         }
 
         antinodes
     }
+
+    fn find_unique_antinodes(&self, rule: Rule) -> HashSet<(usize, usize)> {
+        self.antinodes(rule).into_iter()
+            .map(|antinode| antinode.pos)
+            .collect()
+    }
+
+    /// Returns a copy of the map with antinodes overlaid on empty cells,
+    /// either for a single frequency (other antennas are hidden) or for all.
+    /// As in the puzzle examples, antennas take precedence over antinodes.
+    fn overlay_antinodes(&self, rule: Rule, freq: Option<char>) -> Map {
+        let mut map = self.clone();
+
+        if let Some(freq) = freq {
+            map.antennas.retain(|&c, _| c == freq);
+
+            for cell in map.cells.iter_mut().flatten() {
+                if matches!(cell, Cell::Antenna(c) if *c != freq) {
+                    *cell = Cell::Empty;
+                }
+            }
+        }
+
+        for pos in map.find_unique_antinodes(rule) {
+            let cell = &mut map.cells[pos.0][pos.1];
+
+            if matches!(cell, Cell::Empty) {
+                *cell = Cell::Antinode;
+            }
+        }
+
+        map
+    }
+
+    /// Returns `(frequency, #antennas, #unique antinodes)`, sorted by frequency.
+    fn legend(&self, rule: Rule) -> Vec<(char, usize, usize)> {
+        let mut unique = HashMap::<char, HashSet<_>>::new();
+
+        for antinode in self.antinodes(rule) {
+            unique.entry(antinode.freq).or_default().insert(antinode.pos);
+        }
+
+        self.antennas.iter()
+            .map(|(&freq, locations)| {
+                (freq, locations.len(), unique.get(&freq).map_or(0, |s| s.len()))
+            })
+            .sorted()
+            .collect()
+    }
+}
+
+impl std::fmt::Display for Cell {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", match self {
+            Cell::Antinode => '#',
+            Cell::Antenna(c) => *c,
+            Cell::Empty => '.',
+        })
+    }
+}
+
+impl std::fmt::Display for Map {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for row in &self.cells {
+            for cell in row {
+                write!(f, "{}", cell)?;
+            }
+            writeln!(f)?;
+        }
+
+        Ok(())
+    }
 }
 
 impl TryFrom<&str> for Map {
@@ -123,11 +218,104 @@ impl TryFrom<&str> for Map {
 fn main() {
     let map: Map = INPUT.try_into().unwrap();
 
-    let antinodes1 = map.find_unique_antinodes(Map::antinodes1);
+    let antinodes1 = map.find_unique_antinodes(Rule::Twice);
 
     println!("{:?}", antinodes1.len());
 
-    let antinodes2 = map.find_unique_antinodes(Map::antinodes_infinity);
+    let antinodes2 = map.find_unique_antinodes(Rule::Collinear);
 
     println!("{:?}", antinodes2.len());
-}
\ No newline at end of file
+
+    let args = std::env::args().collect::<Vec<_>>();
+
+    // e.g., `cargo run --bin day8 -- --render A`
+    if let Some(i) = args.iter().position(|arg| arg == "--render") {
+        let freq = args.get(i + 1).and_then(|arg| arg.chars().next());
+
+        print!("{}", map.overlay_antinodes(Rule::Collinear, freq));
+
+        for (freq, num_antennas, num_antinodes) in map.legend(Rule::Collinear) {
+            println!("{}: {} antennas, {} antinodes", freq, num_antennas, num_antinodes);
+        }
+    }
+
+    if args.iter().any(|arg| arg == "--pairs") {
+        for antinode in map.antinodes(Rule::Collinear) {
+            println!(
+                "{:?} {} {:?} {:?}",
+                antinode.pos, antinode.freq, antinode.pair.0, antinode.pair.1,
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const SAMPLE: &str = include_str!("./day8-sample.txt");
+
+    #[test]
+    fn test_sample() {
+        let map: Map = SAMPLE.try_into().unwrap();
+
+        assert_eq!(map.find_unique_antinodes(Rule::Twice).len(), 14);
+        assert_eq!(map.find_unique_antinodes(Rule::Collinear).len(), 34);
+    }
+
+    #[test]
+    fn test_render() {
+        let map: Map = SAMPLE.try_into().unwrap();
+
+        assert_eq!(
+            format!("{}", map.overlay_antinodes(Rule::Twice, None)),
+            "\
+......#....#
+...#....0...
+....#0....#.
+..#....0....
+....0....#..
+.#....A.....
+...#........
+#......#....
+........A...
+.........A..
+..........#.
+..........#.
+");
+
+        assert_eq!(map.legend(Rule::Collinear), vec![('0', 4, 21), ('A', 3, 16)]);
+    }
+
+    #[test]
+    fn test_collinear_non_primitive_delta() {
+        let map: Map = "\
+a.........
+..........
+....a.....".try_into().unwrap();
+
+        let antinodes = map.find_unique_antinodes(Rule::Collinear);
+
+        assert_eq!(antinodes, HashSet::from([(0, 0), (1, 2), (2, 4)]));
+    }
+
+    #[test]
+    fn test_antinode_pairs() {
+        let map: Map = "\
+......
+..a...
+...a..
+......".try_into().unwrap();
+
+        let antinodes = map.antinodes(Rule::Twice);
+
+        assert_eq!(antinodes.len(), 2);
+        assert!(antinodes.iter().all(|antinode| {
+            antinode.freq == 'a' && antinode.pair == ((1, 2), (2, 3))
+        }));
+        assert_eq!(
+            antinodes.iter().map(|antinode| antinode.pos).collect::<HashSet<_>>(),
+            HashSet::from([(0, 1), (3, 4)]),
+        );
+    }
+}
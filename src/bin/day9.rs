@@ -1,126 +1,352 @@
-use std::fmt;
+use std::{cmp::Reverse, collections::{BTreeMap, BTreeSet, VecDeque}, fmt};
 
+use aoc2024::Args;
 use itertools::Itertools;
 
 const INPUT: &str = include_str!("./day9.txt");
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq)]
 enum BlockKind {
     Id(u32),
     Free,
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq)]
 struct Block {
     kind: BlockKind,
     size: u32,
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq)]
 struct FileSystem(Vec<Block>);
 
+/// A dense disk map (alternating file and free span lengths) together with
+/// the file ID of each file span.
+///
+/// For a freshly parsed disk the IDs are simply `0..n`, but a compacted
+/// layout has files out of order (or split), so the IDs are kept alongside
+/// the digits to make the conversion lossless.
+#[derive(Debug, PartialEq)]
+struct DiskMap {
+    digits: String,
+    /// `None` marks a zero-length placeholder file, needed to encode two
+    /// adjacent free spans (e.g., leading free space, or a free span longer
+    /// than 9 blocks).
+    ids: Vec<Option<u32>>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Strategy {
+    /// Moves single blocks from the end into the leftmost free block (part 1).
+    Blocks,
+    /// Moves whole files to the leftmost free span that fits (part 2).
+    FirstFit,
+    /// Moves whole files to the smallest free span that fits, preferring the
+    /// leftmost one on ties.
+    BestFit,
+    /// Moves whole files, lowest ID first, to the rightmost free span that
+    /// fits, packing them towards the end of the disk.
+    ToEnd,
+}
+
+impl Strategy {
+    const ALL: [Strategy; 4] = [
+        Strategy::Blocks,
+        Strategy::FirstFit,
+        Strategy::BestFit,
+        Strategy::ToEnd,
+    ];
+}
+
+impl TryFrom<&str> for Strategy {
+    type Error = &'static str;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        match value {
+            "blocks" => Ok(Strategy::Blocks),
+            "first-fit" => Ok(Strategy::FirstFit),
+            "best-fit" => Ok(Strategy::BestFit),
+            "to-end" => Ok(Strategy::ToEnd),
+            _ => Err("unknown strategy"),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Report {
+    strategy: Strategy,
+    /// Number of blocks moved for `Strategy::Blocks`, files moved otherwise.
+    moves: usize,
+    /// Number of free spans left after coalescing.
+    free_spans: usize,
+    /// `1 - (largest free span / total free space)`; 0 if all free space is
+    /// contiguous.
+    fragmentation: f64,
+    csum: usize,
+}
+
+/// The free spans of a disk, by offset (to merge a span with the spans
+/// next to it) and by length (to find a span that fits).
+#[derive(Default)]
+struct FreeSpans {
+    /// Length of the span at each offset.
+    by_offset: BTreeMap<usize, usize>,
+    /// Offsets of the spans of each length.
+    by_len: BTreeMap<usize, BTreeSet<usize>>,
+}
+
+impl FreeSpans {
+    /// Frees `len` blocks at `offset`, merging them with the spans right
+    /// before and after.
+    fn insert(&mut self, mut offset: usize, mut len: usize) {
+        if len == 0 {
+            return;
+        }
+
+        if let Some((&o, &l)) = self.by_offset.range(..offset).next_back() {
+            if o + l == offset {
+                self.remove(o);
+                (offset, len) = (o, l + len);
+            }
+        }
+
+        if let Some(l) = self.by_offset.get(&(offset + len)).copied() {
+            self.remove(offset + len);
+            len += l;
+        }
+
+        self.by_offset.insert(offset, len);
+        self.by_len.entry(len).or_default().insert(offset);
+    }
+
+    /// Removes the span at `offset`, returning its length.
+    fn remove(&mut self, offset: usize) -> usize {
+        let len = self.by_offset.remove(&offset).unwrap();
+
+        let offsets = self.by_len.get_mut(&len).unwrap();
+
+        offsets.remove(&offset);
+
+        if offsets.is_empty() {
+            self.by_len.remove(&len);
+        }
+
+        len
+    }
+
+    /// A span of at least `size` blocks that starts before `before`: the
+    /// leftmost one, or with `best_fit` the smallest one (the leftmost on
+    /// ties). Only the first span of each length has to be looked at.
+    fn find(&self, size: usize, before: usize, best_fit: bool) -> Option<usize> {
+        let mut candidates = self.by_len.range(size.max(1)..)
+            .filter_map(|(_, offsets)| offsets.first().copied())
+            .filter(|&o| o < before);
+
+        if best_fit {
+            candidates.next()
+        } else {
+            candidates.min()
+        }
+    }
+}
+
 impl FileSystem {
-    fn compress_once1(&mut self) -> Result<(), &'static str> {
-        let tail = loop {
-            if  let Some(block) = self.0.pop() {
-                match block.kind {
-                    BlockKind::Free => continue,
-                    BlockKind::Id(_) => { break block; }
-                }
+    fn len(&self) -> usize {
+        self.0.iter().map(|block| block.size as usize).sum()
+    }
+
+    /// Moves file blocks one at a time from the end of the disk to the
+    /// leftmost free block, consuming the layout from both ends at once.
+    /// Returns the number of blocks moved.
+    fn compress1(&mut self) -> usize {
+        let len = self.len();
+
+        let mut moves = 0;
+
+        let mut blocks = std::mem::take(&mut self.0).into_iter()
+            .collect::<VecDeque<_>>();
+
+        while let Some(block) = blocks.pop_front() {
+            if let BlockKind::Id(_) = block.kind {
+                self.0.push(block);
+                continue;
             }
-        };
 
-        for i in 0..self.0.len() {
-            if matches!(self.0[i].kind, BlockKind::Free) {
-                let block = self.0.remove(i);
-
-                if tail.size <= block.size {
-                    self.0.insert(i, Block {
-                        kind: tail.kind,
-                        size: tail.size,
-                    });
-
-                    if tail.size < block.size {
-                        self.0.insert(i + 1, Block {
-                            kind: BlockKind::Free,
-                            size: block.size - tail.size,
-                        });
-                    }
-                } else {
-                    self.0.insert(i, Block {
-                        kind: tail.kind,
-                        size: block.size,
-                    });
-
-                    self.0.push(Block {
-                        kind: tail.kind,
-                        size: tail.size - block.size,
-                    });
+            let mut space = block.size;
+
+            while space > 0 {
+                let Some(mut tail) = blocks.pop_back() else {
+                    break;
+                };
+
+                if let BlockKind::Free = tail.kind {
+                    continue;
                 }
 
-                return Ok(())
+                let size = tail.size.min(space);
+
+                self.0.push(Block { kind: tail.kind, size });
+
+                moves += size as usize;
+                space -= size;
+                tail.size -= size;
+
+                if tail.size > 0 {
+                    blocks.push_back(tail);
+                }
             }
         }
 
-        self.0.push(tail);
+        self.pad_to(len);
 
-        Err("meh")
+        moves
     }
 
-    fn compress1(&mut self) {
-        while let Ok(_) = self.compress_once1() {
+    /// Moves each whole file, in decreasing order of file ID, to a free
+    /// span to its left that can hold it: the leftmost such span, or with
+    /// `best_fit` the smallest one. Returns the number of files moved.
+    ///
+    /// The space a file leaves behind is free for the files after it, which
+    /// matters when IDs don't increase from left to right (e.g., for a disk
+    /// map read back with its IDs).
+    fn compress_files(&mut self, best_fit: bool) -> usize {
+        let len = self.len();
+
+        let mut moves = 0;
+
+        let mut free = FreeSpans::default();
+
+        // (offset, id, size)
+        let mut files = Vec::new();
+
+        let mut offset = 0;
+
+        for block in &self.0 {
+            match block.kind {
+                BlockKind::Id(id) => files.push((offset, id, block.size as usize)),
+                BlockKind::Free => free.insert(offset, block.size as usize),
+            }
+
+            offset += block.size as usize;
+        }
+
+        files.sort_by_key(|&(_, id, _)| Reverse(id));
+
+        for file in files.iter_mut() {
+            let (offset, _, size) = *file;
+
+            if let Some(o) = free.find(size, offset, best_fit) {
+                let len = free.remove(o);
+
+                free.insert(o + size, len - size);
+                free.insert(offset, size);
+
+                file.0 = o;
+                moves += 1;
+            }
         }
+
+        files.sort();
+
+        self.0.clear();
+
+        let mut offset = 0;
+
+        for (o, id, size) in files {
+            if o > offset {
+                self.0.push(Block { kind: BlockKind::Free, size: (o - offset) as u32 });
+            }
+
+            self.0.push(Block { kind: BlockKind::Id(id), size: size as u32 });
+
+            offset = o + size;
+        }
+
+        self.pad_to(len);
+
+        moves
     }
 
-    fn compress_once2(&mut self) -> Result<(), &'static str> {
-        for i in (0..self.0.len()).rev() {
-            let block = self.0[i].clone();
+    fn compress2(&mut self) -> usize {
+        self.compress_files(false)
+    }
 
-            if matches!(&block.kind, BlockKind::Free) {
-                continue;
+    /// Mirrors the disk so that moving files to the left (in decreasing
+    /// order of ID) becomes moving them to the right in increasing order.
+    fn compress_to_end(&mut self) -> usize {
+        self.0.reverse();
+
+        for block in self.0.iter_mut() {
+            if let BlockKind::Id(id) = &mut block.kind {
+                *id = u32::MAX - *id;
             }
+        }
 
-            let required = block.size;
+        let moves = self.compress_files(false);
 
-            for j in 0..i {
-                let curr = self.0[j].clone();
+        self.0.reverse();
 
-                match curr.kind {
-                    BlockKind::Id(_) => continue,
-                    BlockKind::Free => {
-                        if curr.size >= required {
-                            // XXX: We are not merging newly freed block and
-                            // its free space neighbors...
-                            let block = std::mem::replace(
-                                &mut self.0[i],
-                                Block {
-                                    kind: BlockKind::Free,
-                                    size: required,
-                                });
+        for block in self.0.iter_mut() {
+            if let BlockKind::Id(id) = &mut block.kind {
+                *id = u32::MAX - *id;
+            }
+        }
 
-                            let _ = self.0.remove(j);
+        moves
+    }
 
-                            self.0.insert(j, block.clone());
+    /// Merges adjacent free blocks, as well as adjacent blocks of the same
+    /// file, and drops empty ones.
+    fn coalesce(&mut self) {
+        let mut blocks: Vec<Block> = Vec::with_capacity(self.0.len());
 
-                            if curr.size > required {
-                                self.0.insert(j + 1, Block {
-                                    kind: BlockKind::Free,
-                                    size: curr.size - required,
-                                });
-                            }
+        for block in self.0.drain(..) {
+            if block.size == 0 {
+                continue;
+            }
 
-                            return Ok(());
-                        }
-                    }
-                }
+            match blocks.last_mut() {
+                Some(last) if last.kind == block.kind => last.size += block.size,
+                _ => blocks.push(block),
             }
         }
 
-        Err("meheh")
+        self.0 = blocks;
     }
 
-    fn compress2(&mut self) {
-        while let Ok(_) = self.compress_once2() {
+    fn compact(&mut self, strategy: Strategy) -> Report {
+        let moves = match strategy {
+            Strategy::Blocks => self.compress1(),
+            Strategy::FirstFit => self.compress_files(false),
+            Strategy::BestFit => self.compress_files(true),
+            Strategy::ToEnd => self.compress_to_end(),
+        };
+
+        self.coalesce();
+
+        let free = self.0.iter()
+            .filter(|block| matches!(block.kind, BlockKind::Free))
+            .map(|block| block.size as usize)
+            .collect::<Vec<_>>();
+
+        let total = free.iter().sum::<usize>();
+        let largest = free.iter().max().copied().unwrap_or(0);
+
+        Report {
+            strategy,
+            moves,
+            free_spans: free.len(),
+            fragmentation: if total == 0 { 0.0 } else { 1.0 - largest as f64 / total as f64 },
+            csum: self.csum(),
+        }
+    }
+
+    /// Appends free space so that the disk spans `len` blocks again.
+    fn pad_to(&mut self, len: usize) {
+        let curr = self.len();
+
+        if curr < len {
+            self.0.push(Block { kind: BlockKind::Free, size: (len - curr) as u32 });
         }
     }
 
@@ -137,56 +363,176 @@ impl FileSystem {
     }
 }
 
+impl From<&FileSystem> for DiskMap {
+    fn from(filesystem: &FileSystem) -> Self {
+        let mut digits = String::new();
+        let mut ids = Vec::new();
+
+        let mut expect_file = true;
+
+        let mut push = |id: Option<Option<u32>>, size: u32| {
+            let is_file = id.is_some();
+
+            // Keep files and free spans alternating by inserting an empty
+            // span of the other kind.
+            if is_file != expect_file {
+                digits.push('0');
+
+                if expect_file {
+                    ids.push(None);
+                }
+
+                expect_file = !expect_file;
+            }
+
+            digits.push(char::from_digit(size, 10).unwrap());
+
+            if let Some(id) = id {
+                ids.push(id);
+            }
+
+            expect_file = !expect_file;
+        };
+
+        for block in &filesystem.0 {
+            let id = match block.kind {
+                BlockKind::Id(id) => Some(Some(id)),
+                BlockKind::Free => None,
+            };
+
+            let mut size = block.size;
+
+            loop {
+                let chunk = size.min(9);
+
+                push(id, chunk);
+
+                size -= chunk;
+
+                if size == 0 {
+                    break;
+                }
+            }
+        }
+
+        DiskMap { digits, ids }
+    }
+}
+
+impl TryFrom<&DiskMap> for FileSystem {
+    type Error = &'static str;
+
+    fn try_from(value: &DiskMap) -> Result<Self, Self::Error> {
+        let mut blocks = Vec::new();
+
+        let mut ids = value.ids.iter();
+
+        for (i, c) in value.digits.chars().enumerate() {
+            let size = c.to_digit(10).ok_or("invalid digit in disk map")?;
+
+            if i % 2 == 1 {
+                blocks.push(Block { kind: BlockKind::Free, size });
+                continue;
+            }
+
+            match ids.next().ok_or("missing file ID")? {
+                Some(id) => blocks.push(Block { kind: BlockKind::Id(*id), size }),
+                None if size == 0 => {},
+                None => return Err("placeholder file must be empty"),
+            }
+        }
+
+        if ids.next().is_some() {
+            return Err("too many file IDs");
+        }
+
+        Ok(FileSystem(blocks))
+    }
+}
+
+/// Reads a disk map as written by its `Display` impl: the digits, then
+/// optionally a line of comma-separated file IDs. Without that line, the
+/// files are numbered `0..n` as in the puzzle input.
+impl TryFrom<&str> for DiskMap {
+    type Error = &'static str;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        let mut lines = value.lines().map(str::trim).filter(|line| !line.is_empty());
+
+        let digits = lines.next().unwrap_or_default().to_string();
+
+        let ids = match lines.next() {
+            Some(line) => line.split(',')
+                .map(|id| match id.trim() {
+                    "." => Ok(None),
+                    id => id.parse::<u32>().map(Some).map_err(|_| "invalid file ID"),
+                })
+                .collect::<Result<Vec<_>, _>>()?,
+            None => (0..digits.len().div_ceil(2) as u32).map(Some).collect(),
+        };
+
+        if lines.next().is_some() {
+            return Err("unexpected line after file IDs");
+        }
+
+        Ok(DiskMap { digits, ids })
+    }
+}
+
+/// Writes the digits and, on a second line, the file IDs, with `.` for
+/// placeholder files.
+impl fmt::Display for DiskMap {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{}", self.digits)?;
+        writeln!(f, "{}", self.ids.iter()
+            .map(|id| id.map_or(String::from("."), |id| id.to_string()))
+            .join(","))
+    }
+}
+
 impl TryFrom<&str> for FileSystem {
     type Error = &'static str;
 
     fn try_from(value: &str) -> Result<Self, Self::Error> {
-        Ok(FileSystem(
-            value.chars()
-                .map(|c| c.to_digit(10).unwrap())
-                .chunks(2)
-                .into_iter()
-                .enumerate()
-                .flat_map(|(id, mut chunk)| {
-                    let mut blocks = Vec::new();
-
-                    if let Some(allocated_space) = chunk.next() {
-                        blocks.push(Block {
-                            kind: BlockKind::Id(id as u32),
-                            size: allocated_space,
-                        })
-                    }
-
-                    if let Some(free_space) = chunk.next() {
-                        blocks.push(Block {
-                            kind: BlockKind::Free,
-                            size: free_space,
-                        })
-                    }
-
-                    blocks
-            })
-            .collect::<Vec::<_>>()
-        ))
+        FileSystem::try_from(&DiskMap::try_from(value)?)
     }
 }
 
+/// Dumps the layout as a table of `(id, offset, length)`, with `.` as the
+/// ID of free spans.
 impl fmt::Display for FileSystem {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        self.0.iter().for_each(|block| {
-            // FIXME
-            write!(f, "{}", match block.kind {
-                BlockKind::Free => String::from(".").repeat(block.size as usize),
-                BlockKind::Id(id) => id.to_string().repeat(block.size as usize),
-            });
-        });
+        writeln!(f, "{:>8} {:>8} {:>8}", "id", "offset", "length")?;
+
+        let mut offset = 0;
+
+        for block in &self.0 {
+            match block.kind {
+                BlockKind::Id(id) => writeln!(f, "{:>8} {:>8} {:>8}", id, offset, block.size)?,
+                BlockKind::Free => writeln!(f, "{:>8} {:>8} {:>8}", ".", offset, block.size)?,
+            }
+
+            offset += block.size as usize;
+        }
 
         Ok(())
     }
 }
 
 fn main() {
-    let filesystem: FileSystem = INPUT.try_into().unwrap();
+    let args = match Args::parse(std::env::args().skip(1), &["--input"]) {
+        Ok(args) => args,
+        Err(e) => {
+            println!("error: {}", e);
+            return;
+        },
+    };
+
+    // e.g., `cargo run --bin day9 -- --input dump.txt`, where `dump.txt` was
+    // written with `--disk-map`
+    let input = args.value("--input").map(|path| std::fs::read_to_string(path).unwrap());
+
+    let filesystem: FileSystem = input.as_deref().unwrap_or(INPUT).try_into().unwrap();
 
     let mut fs1 = filesystem.clone();
 
@@ -199,4 +545,189 @@ fn main() {
     fs2.compress2();
 
     println!("{}", fs2.csum());
+
+    let dump_table = args.has("--table");
+    let dump_disk_map = args.has("--disk-map");
+
+    // e.g., `cargo run --bin day9 -- best-fit to-end`, or `-- all --table`
+    let strategies = args.positional().iter()
+        .flat_map(|arg| match arg.as_str() {
+            "all" => Strategy::ALL.to_vec(),
+            arg => vec![Strategy::try_from(arg).unwrap()],
+        })
+        .collect::<Vec<_>>();
+
+    for strategy in strategies {
+        let mut fs = filesystem.clone();
+
+        let report = fs.compact(strategy);
+
+        println!(
+            "{:?}: moves={} free_spans={} fragmentation={:.4} csum={}",
+            report.strategy, report.moves, report.free_spans,
+            report.fragmentation, report.csum,
+        );
+
+        if dump_table {
+            print!("{}", fs);
+        }
+
+        if dump_disk_map {
+            print!("{}", DiskMap::from(&fs));
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const SAMPLE: &str = include_str!("./day9-sample.txt");
+
+    #[test]
+    fn test_compress() {
+        let filesystem: FileSystem = SAMPLE.try_into().unwrap();
+
+        let mut fs1 = filesystem.clone();
+
+        fs1.compress1();
+
+        assert_eq!(fs1.csum(), 1928);
+        assert_eq!(fs1.len(), filesystem.len());
+
+        let mut fs2 = filesystem.clone();
+
+        fs2.compress2();
+
+        assert_eq!(fs2.csum(), 2858);
+        assert_eq!(fs2.len(), filesystem.len());
+    }
+
+    #[test]
+    fn test_compress_ids_out_of_order() {
+        // File 2 moves out of the way of file 1, which can then take its
+        // place ...
+        let mut fs: FileSystem = "02202\n.,2,1".try_into().unwrap();
+
+        fs.compress2();
+        fs.coalesce();
+
+        assert_eq!(fs, FileSystem(vec![
+            Block { kind: BlockKind::Id(2), size: 2 },
+            Block { kind: BlockKind::Id(1), size: 2 },
+            Block { kind: BlockKind::Free, size: 2 },
+        ]));
+
+        // ... or the space it leaves, together with the free block after it.
+        let mut fs: FileSystem = "01112\n.,2,1".try_into().unwrap();
+
+        fs.compress2();
+        fs.coalesce();
+
+        assert_eq!(fs, FileSystem(vec![
+            Block { kind: BlockKind::Id(2), size: 1 },
+            Block { kind: BlockKind::Id(1), size: 2 },
+            Block { kind: BlockKind::Free, size: 2 },
+        ]));
+    }
+
+    #[test]
+    fn test_coalesce() {
+        let mut filesystem = FileSystem(vec![
+            Block { kind: BlockKind::Id(0), size: 1 },
+            Block { kind: BlockKind::Free, size: 1 },
+            Block { kind: BlockKind::Free, size: 0 },
+            Block { kind: BlockKind::Free, size: 2 },
+            Block { kind: BlockKind::Id(1), size: 0 },
+            Block { kind: BlockKind::Id(2), size: 1 },
+        ]);
+
+        filesystem.coalesce();
+
+        assert_eq!(filesystem.0.len(), 3);
+        assert!(matches!(filesystem.0[1], Block { kind: BlockKind::Free, size: 3 }));
+    }
+
+    #[test]
+    fn test_disk_map_round_trip() {
+        let filesystem: FileSystem = SAMPLE.try_into().unwrap();
+
+        assert_eq!(DiskMap::from(&filesystem).digits, SAMPLE.trim());
+
+        for strategy in Strategy::ALL {
+            let mut fs = filesystem.clone();
+
+            fs.compact(strategy);
+
+            let dump = DiskMap::from(&fs).to_string();
+
+            let mut round_trip = FileSystem::try_from(dump.as_str()).unwrap();
+
+            round_trip.coalesce();
+
+            assert_eq!(round_trip, fs);
+        }
+    }
+
+    #[test]
+    fn test_disk_map_long_spans() {
+        let fs = FileSystem(vec![
+            Block { kind: BlockKind::Free, size: 12 },
+            Block { kind: BlockKind::Id(42), size: 10 },
+            Block { kind: BlockKind::Id(7), size: 1 },
+        ]);
+
+        let disk_map = DiskMap::from(&fs);
+
+        assert_eq!(disk_map.digits, "090390101");
+        assert_eq!(disk_map.ids, vec![None, None, Some(42), Some(42), Some(7)]);
+
+        let mut round_trip = FileSystem::try_from(&disk_map).unwrap();
+
+        round_trip.coalesce();
+
+        assert_eq!(round_trip, fs);
+
+        // The dump reads back in, too.
+        assert_eq!(disk_map.to_string(), "090390101\n.,.,42,42,7\n");
+        assert_eq!(DiskMap::try_from(disk_map.to_string().as_str()), Ok(disk_map));
+    }
+
+    #[test]
+    fn test_fmt() {
+        let fs: FileSystem = "12".try_into().unwrap();
+
+        assert_eq!(
+            format!("{}", fs),
+            concat!(
+                "      id   offset   length\n",
+                "       0        0        1\n",
+                "       .        1        2\n",
+            ),
+        );
+    }
+
+    #[test]
+    fn test_compact() {
+        let filesystem: FileSystem = SAMPLE.try_into().unwrap();
+
+        let reports = Strategy::ALL.map(|strategy| filesystem.clone().compact(strategy));
+
+        assert_eq!(reports[0].csum, 1928);
+        assert_eq!(reports[0].free_spans, 1);
+        assert_eq!(reports[0].fragmentation, 0.0);
+        assert_eq!(reports[1].csum, 2858);
+
+        for report in &reports {
+            let mut fs = filesystem.clone();
+            fs.compact(report.strategy);
+            assert_eq!(fs.len(), filesystem.len());
+        }
+
+        // Everything ends up at the end of the disk, so only the leading
+        // free span remains.
+        let mut fs = filesystem.clone();
+        fs.compact(Strategy::ToEnd);
+        assert!(matches!(fs.0[0].kind, BlockKind::Free));
+    }
 }
@@ -0,0 +1,111 @@
+//! Code shared by more than one day's solution.
+
+pub mod search;
+
+/// Command-line arguments, split in one pass into options that take a value
+/// (e.g., `--routes 3`), switches (e.g., `--trace`) and positional
+/// arguments.
+#[derive(Debug, Default)]
+pub struct Args {
+    options: Vec<(String, String)>,
+    switches: Vec<String>,
+    positional: Vec<String>,
+}
+
+impl Args {
+    /// Splits `args`, where `options` are the flags that take a value. Fails
+    /// if one of them is the last argument, i.e., has no value.
+    pub fn parse(
+        args: impl IntoIterator<Item = String>,
+        options: &[&str],
+    ) -> Result<Self, &'static str> {
+        let mut parsed = Args::default();
+        let mut args = args.into_iter();
+
+        while let Some(arg) = args.next() {
+            if options.contains(&arg.as_str()) {
+                let value = args.next().ok_or("missing option value")?;
+
+                parsed.options.push((arg, value));
+            } else if arg.starts_with("--") {
+                parsed.switches.push(arg);
+            } else {
+                parsed.positional.push(arg);
+            }
+        }
+
+        Ok(parsed)
+    }
+
+    /// The value of `option`, the last one if it's given more than once.
+    pub fn value(&self, option: &str) -> Option<&str> {
+        self.options.iter().rev()
+            .find(|(name, _)| name == option)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Whether `switch` is given.
+    pub fn has(&self, switch: &str) -> bool {
+        self.switches.iter().any(|arg| arg == switch)
+    }
+
+    /// The arguments that are neither flags nor option values, in order.
+    pub fn positional(&self) -> &[String] {
+        &self.positional
+    }
+}
+
+/// Returns `(g, x, y)` such that `a * x + b * y = g = gcd(a, b)`, with
+/// `g >= 0`.
+pub fn ext_gcd(a: i128, b: i128) -> (i128, i128, i128) {
+    if b == 0 {
+        (a.abs(), a.signum(), 0)
+    } else {
+        let (g, x, y) = ext_gcd(b, a % b);
+
+        (g, y, x - (a / b) * y)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn parse(args: &str) -> Result<Args, &'static str> {
+        Args::parse(args.split_whitespace().map(String::from), &["--input", "--offset"])
+    }
+
+    #[test]
+    fn test_args() {
+        let args = parse("all --input dump.txt --table best-fit --offset -5 --offset 3").unwrap();
+
+        assert_eq!(args.value("--input"), Some("dump.txt"));
+        assert_eq!(args.value("--offset"), Some("3"));
+        assert_eq!(args.value("--table"), None);
+        assert!(args.has("--table"));
+        assert!(!args.has("--input"));
+        assert_eq!(args.positional(), ["all", "best-fit"]);
+
+        assert_eq!(parse("--table --input").err(), Some("missing option value"));
+    }
+
+    #[test]
+    fn test_ext_gcd() {
+        for a in -20..=20 {
+            for b in -20..=20 {
+                let (g, x, y) = ext_gcd(a, b);
+
+                assert_eq!(a * x + b * y, g);
+                assert!(g >= 0);
+
+                if g != 0 {
+                    assert_eq!((a % g, b % g), (0, 0));
+                    assert!((1..=20).filter(|d| a % d == 0 && b % d == 0).all(|d| d <= g));
+                }
+            }
+        }
+
+        assert_eq!(ext_gcd(0, 0), (0, 0, 0));
+        assert_eq!(ext_gcd(240, 46).0, 2);
+    }
+}
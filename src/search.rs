@@ -0,0 +1,408 @@
+//! Shortest paths over weighted graphs.
+//!
+//! A graph is given by a successor function that maps a node to its
+//! neighbors and the cost of the edge to each of them, so nodes can be
+//! anything hashable, e.g., grid positions or (position, direction) pairs.
+
+use std::{
+    collections::{BinaryHeap, HashMap, HashSet},
+    hash::Hash,
+};
+
+/// The result of a search: the cost of the cheapest path to every node
+/// reached, and every predecessor on such a path. `prev` describes the DAG
+/// of all shortest paths, except when the search only kept one predecessor
+/// per node (`dijkstra`, `astar`).
+#[derive(Debug, Clone)]
+pub struct ShortestPaths<N> {
+    pub dist: HashMap<N, usize>,
+    pub prev: HashMap<N, Vec<N>>,
+}
+
+impl<N: Clone + Eq + Hash> ShortestPaths<N> {
+    /// One cheapest path from a start to `goal`, both included.
+    pub fn path_to(&self, goal: &N) -> Option<Vec<N>> {
+        if !self.dist.contains_key(goal) {
+            return None;
+        }
+
+        let mut path = vec![goal.clone()];
+
+        while let Some(p) = self.prev.get(path.last().unwrap()).and_then(|prev| prev.first()) {
+            path.push(p.clone());
+        }
+
+        path.reverse();
+
+        Some(path)
+    }
+
+    /// Every node on some cheapest path from a start to one of `goals`.
+    pub fn nodes_on_paths_to(&self, goals: impl IntoIterator<Item = N>) -> HashSet<N> {
+        let mut nodes = HashSet::new();
+
+        let mut stack = goals.into_iter()
+            .filter(|goal| self.dist.contains_key(goal))
+            .collect::<Vec<_>>();
+
+        nodes.extend(stack.iter().cloned());
+
+        while let Some(node) = stack.pop() {
+            for p in self.prev.get(&node).into_iter().flatten() {
+                if nodes.insert(p.clone()) {
+                    stack.push(p.clone());
+                }
+            }
+        }
+
+        nodes
+    }
+}
+
+/// A node waiting in the priority queue. Ordered so that `BinaryHeap`, a
+/// max-heap, pops the lowest `priority` first.
+struct Queued<N> {
+    priority: usize,
+    cost: usize,
+    node: N,
+}
+
+impl<N> PartialEq for Queued<N> {
+    fn eq(&self, other: &Self) -> bool {
+        self.priority == other.priority
+    }
+}
+
+impl<N> Eq for Queued<N> {}
+
+impl<N> PartialOrd for Queued<N> {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<N> Ord for Queued<N> {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        other.priority.cmp(&self.priority)
+    }
+}
+
+/// Best-first search from `starts` that stops once it pops a node satisfying
+/// `is_goal`, and returns that node. `heuristic` must never overestimate the
+/// cost to a goal; with a zero heuristic this is Dijkstra's algorithm.
+fn search<N, I>(
+    starts: impl IntoIterator<Item = N>,
+    mut successors: impl FnMut(&N) -> I,
+    heuristic: impl Fn(&N) -> usize,
+    mut is_goal: impl FnMut(&N) -> bool,
+    all_prev: bool,
+) -> (ShortestPaths<N>, Option<N>)
+where
+    N: Clone + Eq + Hash,
+    I: IntoIterator<Item = (N, usize)>,
+{
+    let mut dist = HashMap::new();
+    let mut prev: HashMap<N, Vec<N>> = HashMap::new();
+    let mut pq = BinaryHeap::new();
+
+    for start in starts {
+        dist.insert(start.clone(), 0);
+        prev.insert(start.clone(), Vec::new());
+        pq.push(Queued { priority: heuristic(&start), cost: 0, node: start });
+    }
+
+    while let Some(Queued { cost, node: u, .. }) = pq.pop() {
+        if cost > dist[&u] {
+            // A cheaper path to `u` has been expanded already.
+            continue;
+        }
+
+        if is_goal(&u) {
+            return (ShortestPaths { dist, prev }, Some(u));
+        }
+
+        for (v, w) in successors(&u) {
+            let alt = cost + w;
+
+            match dist.get(&v) {
+                Some(&d) if alt > d => {},
+                Some(&d) if alt == d => {
+                    if all_prev {
+                        prev.entry(v).or_default().push(u.clone());
+                    }
+                },
+                _ => {
+                    dist.insert(v.clone(), alt);
+                    prev.insert(v.clone(), vec![u.clone()]);
+                    pq.push(Queued { priority: alt + heuristic(&v), cost: alt, node: v });
+                },
+            }
+        }
+    }
+
+    (ShortestPaths { dist, prev }, None)
+}
+
+/// Dijkstra's algorithm: the cost of the cheapest path from any of `starts`
+/// to every reachable node, and one predecessor on such a path.
+pub fn dijkstra<N, I>(
+    starts: impl IntoIterator<Item = N>,
+    successors: impl FnMut(&N) -> I,
+) -> ShortestPaths<N>
+where
+    N: Clone + Eq + Hash,
+    I: IntoIterator<Item = (N, usize)>,
+{
+    search(starts, successors, |_| 0, |_| false, false).0
+}
+
+/// Like `dijkstra`, but keeps every predecessor on a cheapest path, so that
+/// all shortest paths can be recovered, e.g., with
+/// `ShortestPaths::nodes_on_paths_to`.
+pub fn shortest_path_dag<N, I>(
+    starts: impl IntoIterator<Item = N>,
+    successors: impl FnMut(&N) -> I,
+) -> ShortestPaths<N>
+where
+    N: Clone + Eq + Hash,
+    I: IntoIterator<Item = (N, usize)>,
+{
+    search(starts, successors, |_| 0, |_| false, true).0
+}
+
+/// A* search from `start` to the nearest node satisfying `is_goal`; returns
+/// the path, both ends included, and its cost. `heuristic` must never
+/// overestimate the remaining cost, or the path found may not be cheapest.
+pub fn astar<N, I>(
+    start: N,
+    successors: impl FnMut(&N) -> I,
+    heuristic: impl Fn(&N) -> usize,
+    is_goal: impl FnMut(&N) -> bool,
+) -> Option<(Vec<N>, usize)>
+where
+    N: Clone + Eq + Hash,
+    I: IntoIterator<Item = (N, usize)>,
+{
+    let (paths, goal) = search([start], successors, heuristic, is_goal, false);
+
+    let goal = goal?;
+
+    Some((paths.path_to(&goal)?, paths.dist[&goal]))
+}
+
+/// Yen's algorithm: up to `k` cheapest paths from `start` to a node
+/// satisfying `is_goal` that don't visit any node twice, cheapest first,
+/// each with its cost. `heuristic` is used as in `astar`.
+pub fn k_shortest_paths<N, I>(
+    start: N,
+    successors: impl FnMut(&N) -> I,
+    heuristic: impl Fn(&N) -> usize,
+    is_goal: impl FnMut(&N) -> bool,
+    k: usize,
+) -> Vec<(Vec<N>, usize)>
+where
+    N: Clone + Eq + Hash,
+    I: IntoIterator<Item = (N, usize)>,
+{
+    k_shortest_paths_by(start, successors, heuristic, is_goal, N::clone, k)
+}
+
+/// Like `k_shortest_paths`, but no path visits two nodes with the same
+/// `key`, e.g., the same place in different states. Only paths that repeat
+/// no key are searched around, so cheapest paths shouldn't repeat one.
+pub fn k_shortest_paths_by<N, K, I>(
+    start: N,
+    mut successors: impl FnMut(&N) -> I,
+    heuristic: impl Fn(&N) -> usize,
+    mut is_goal: impl FnMut(&N) -> bool,
+    key: impl Fn(&N) -> K,
+    k: usize,
+) -> Vec<(Vec<N>, usize)>
+where
+    N: Clone + Eq + Hash,
+    K: Eq + Hash,
+    I: IntoIterator<Item = (N, usize)>,
+{
+    let repeats_key = |path: &[N]| {
+        let mut seen = HashSet::new();
+
+        !path.iter().all(|n| seen.insert(key(n)))
+    };
+
+    let mut found: Vec<(Vec<N>, usize)> = Vec::new();
+
+    if k == 0 {
+        return found;
+    }
+
+    match astar(start, &mut successors, &heuristic, &mut is_goal) {
+        Some(path) if !repeats_key(&path.0) => found.push(path),
+        _ => return found,
+    }
+
+    let mut candidates: Vec<(Vec<N>, usize)> = Vec::new();
+
+    while found.len() < k {
+        let (last, _) = found.last().unwrap().clone();
+
+        // Cost of `last` up to each of its nodes.
+        let mut costs = vec![0];
+
+        for pair in last.windows(2) {
+            let cost = successors(&pair[0]).into_iter()
+                .filter(|(v, _)| *v == pair[1])
+                .map(|(_, cost)| cost)
+                .min()
+                .unwrap();
+
+            costs.push(costs.last().unwrap() + cost);
+        }
+
+        for i in 0..last.len() - 1 {
+            let (root, spur) = (&last[..i], &last[i]);
+
+            // Edges out of the spur node taken by paths found so far that
+            // share this root, so that the spur path deviates from all of them.
+            let taken = found.iter()
+                .filter(|(path, _)| path.len() > i + 1 && path[..=i] == last[..=i])
+                .map(|(path, _)| path[i + 1].clone())
+                .collect::<HashSet<_>>();
+
+            let root_keys = root.iter().chain([spur]).map(&key).collect::<HashSet<_>>();
+
+            let spur_path = astar(
+                spur.clone(),
+                |u: &N| {
+                    successors(u).into_iter()
+                        .filter(|(v, _)| {
+                            !(root_keys.contains(&key(v)) || u == spur && taken.contains(v))
+                        })
+                        .collect::<Vec<_>>()
+                },
+                &heuristic,
+                &mut is_goal,
+            );
+
+            if let Some((spur_path, cost)) = spur_path {
+                let path = root.iter().cloned().chain(spur_path).collect::<Vec<_>>();
+
+                let is_new = !candidates.iter().chain(&found).any(|(p, _)| *p == path);
+
+                if is_new && !repeats_key(&path) {
+                    candidates.push((path, costs[i] + cost));
+                }
+            }
+        }
+
+        // The first of the cheapest candidates, so that ties keep the order
+        // in which they were found.
+        let Some(best) = (0..candidates.len()).min_by_key(|&j| candidates[j].1) else {
+            break;
+        };
+
+        found.push(candidates.remove(best));
+    }
+
+    found
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    // 0 --1--> 1 --1--> 3
+    // |                 ^
+    // +--1--> 2 --1-----+
+    // |                 |
+    // +--------5--------+
+    fn successors(&n: &u32) -> Vec<(u32, usize)> {
+        match n {
+            0 => vec![(1, 1), (2, 1), (3, 5)],
+            1 | 2 => vec![(3, 1)],
+            _ => vec![],
+        }
+    }
+
+    #[test]
+    fn test_dijkstra() {
+        let paths = dijkstra([0], successors);
+
+        assert_eq!(paths.dist[&3], 2);
+        assert_eq!(paths.prev[&3].len(), 1);
+        assert_eq!(paths.path_to(&3).unwrap().len(), 3);
+        assert_eq!(paths.path_to(&4), None);
+    }
+
+    #[test]
+    fn test_shortest_path_dag() {
+        let paths = shortest_path_dag([0], successors);
+
+        let mut prev = paths.prev[&3].clone();
+
+        prev.sort();
+
+        assert_eq!(prev, vec![1, 2]);
+        assert_eq!(paths.nodes_on_paths_to([3]), HashSet::from([0, 1, 2, 3]));
+        assert_eq!(paths.nodes_on_paths_to([2]), HashSet::from([0, 2]));
+    }
+
+    #[test]
+    fn test_astar() {
+        // From (0, 0) to (3, 2) on an open grid, around a wall at x = 1.
+        let wall = |(x, y): (i32, i32)| x == 1 && y < 2;
+
+        let successors = |&(x, y): &(i32, i32)| {
+            [(1, 0), (-1, 0), (0, 1), (0, -1)].into_iter()
+                .map(move |(dx, dy)| (x + dx, y + dy))
+                .filter(|&(x, y)| (0..4).contains(&x) && (0..4).contains(&y) && !wall((x, y)))
+                .map(|p| (p, 1))
+                .collect::<Vec<_>>()
+        };
+
+        let heuristic = |&(x, y): &(i32, i32)| ((3 - x).abs() + (2 - y).abs()) as usize;
+
+        let (path, cost) = astar((0, 0), successors, heuristic, |&p| p == (3, 2)).unwrap();
+
+        assert_eq!(cost, 5);
+        assert_eq!(path.len(), 6);
+        assert_eq!(path.first(), Some(&(0, 0)));
+        assert_eq!(path.last(), Some(&(3, 2)));
+
+        assert_eq!(astar((0, 0), successors, heuristic, |&p| p == (9, 9)), None);
+    }
+
+    #[test]
+    fn test_k_shortest_paths() {
+        let paths = k_shortest_paths(0, successors, |_| 0, |&n| n == 3, 5);
+
+        let costs = paths.iter().map(|&(_, cost)| cost).collect::<Vec<_>>();
+
+        assert_eq!(costs, vec![2, 2, 5]);
+        assert_eq!(paths[2].0, vec![0, 3]);
+        assert_ne!(paths[0].0, paths[1].0);
+
+        assert!(k_shortest_paths(0, successors, |_| 0, |&n| n == 4, 5).is_empty());
+    }
+
+    #[test]
+    fn test_k_shortest_paths_by() {
+        // 1 and 11 are the same place, so 0 -> 1 -> 11 -> 3 comes back to it.
+        let successors = |&n: &u32| match n {
+            0 => vec![(1, 1), (2, 3)],
+            1 => vec![(3, 1), (11, 1)],
+            2 => vec![(3, 1)],
+            11 => vec![(3, 5)],
+            _ => vec![],
+        };
+
+        let costs = |paths: Vec<(Vec<u32>, usize)>| {
+            paths.into_iter().map(|(_, cost)| cost).collect::<Vec<_>>()
+        };
+
+        let by_node = k_shortest_paths(0, successors, |_| 0, |&n| n == 3, 5);
+        let by_place = k_shortest_paths_by(0, successors, |_| 0, |&n| n == 3, |&n| n % 10, 5);
+
+        assert_eq!(costs(by_node), vec![2, 4, 7]);
+        assert_eq!(costs(by_place), vec![2, 4]);
+    }
+}